@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::fs::GitHubFS;
+
+/// Timings and counters collected by `githubfs bench`.
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    pub repository: String,
+    pub first_listing: Duration,
+    pub first_listing_entries: usize,
+    pub tree_walk: Duration,
+    pub directories: usize,
+    pub files: usize,
+    pub read_all: Duration,
+    pub files_read: usize,
+    pub bytes_read: u64,
+    pub max_file_size: u64,
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lookups = self.cache_hits + self.cache_misses;
+        let hit_rate = if lookups == 0 { 0.0 } else { self.cache_hits as f64 * 100.0 / lookups as f64 };
+        writeln!(f, "Benchmark for {}", self.repository)?;
+        writeln!(f, "  First listing:  {:>8} ms ({} entries)", self.first_listing.as_millis(), self.first_listing_entries)?;
+        writeln!(f, "  Full tree walk: {:>8} ms ({} directories, {} files)", self.tree_walk.as_millis(), self.directories, self.files)?;
+        writeln!(
+            f,
+            "  Read files:     {:>8} ms ({} files, {} bytes, cap {} bytes)",
+            self.read_all.as_millis(), self.files_read, self.bytes_read, self.max_file_size
+        )?;
+        writeln!(f, "  API requests:   {:>8}", self.requests)?;
+        write!(f, "  Content cache:  {:>8} hits, {} misses ({:.1}% hit rate)", self.cache_hits, self.cache_misses, hit_rate)
+    }
+}
+
+/// Measures how long it takes to list, walk and read `full_name` through
+/// the same fetch layer used by the mount.
+pub fn run(fs: &GitHubFS, full_name: &str, max_file_size: u64) -> io::Result<BenchReport> {
+    // Use the current name in case the repository was renamed
+    let repo = fs.fetch_repository(full_name)?;
+    let full_name = repo.full_name.as_str();
+    info!("Benchmarking {}", full_name);
+
+    let mut report = BenchReport {
+        repository: full_name.to_string(),
+        max_file_size,
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    let root = fs.fetch_directory(full_name, "")?;
+    report.first_listing = started.elapsed();
+    report.first_listing_entries = root.len();
+
+    let started = Instant::now();
+    let mut pending: VecDeque<_> = root.into_iter().collect();
+    let mut files = Vec::new();
+    report.directories = 1;
+    while let Some(entry) = pending.pop_front() {
+        if entry.file_type == "dir" {
+            report.directories += 1;
+            pending.extend(fs.fetch_directory(full_name, &entry.path)?);
+        } else if entry.file_type == "file" {
+            files.push(entry);
+        }
+    }
+    report.tree_walk = started.elapsed();
+    report.files = files.len();
+
+    let started = Instant::now();
+    for file in files.iter().filter(|file| file.size <= max_file_size) {
+        match fs.fetch_file_content(full_name, &file.path) {
+            Ok(content) => {
+                report.files_read += 1;
+                report.bytes_read += content.len() as u64;
+            }
+            Err(err) => debug!("Failed to read {}: {}", file.path, err),
+        }
+    }
+    report.read_all = started.elapsed();
+
+    let metrics = fs.metrics();
+    report.requests = metrics.requests.load(Ordering::Relaxed);
+    report.cache_hits = metrics.cache_hits.load(Ordering::Relaxed);
+    report.cache_misses = metrics.cache_misses.load(Ordering::Relaxed);
+    Ok(report)
+}
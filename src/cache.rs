@@ -0,0 +1,210 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File with the saved ETags, directly under the cache directory.
+const ETAGS_FILE: &str = "etags.json";
+/// Subdirectory with saved file contents, one file per blob SHA.
+const CONTENTS_DIR: &str = "contents";
+
+/// Response of a conditional request, kept to answer a later `304`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EtagResponse {
+    pub etag: String,
+    pub body: Vec<u8>,
+}
+
+/// Counts the body against the cache size.
+impl AsRef<[u8]> for EtagResponse {
+    fn as_ref(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedEtag {
+    key: String,
+    etag: String,
+    /// Body in base64
+    body: String,
+}
+
+/// Summary of what is stored in a cache directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CacheInfo {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// Default persistent cache location: `$XDG_CACHE_HOME/githubfs`, falling back
+/// to `$HOME/.cache/githubfs`.
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir).join("githubfs");
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".cache").join("githubfs"),
+        None => env::temp_dir().join("githubfs-cache"),
+    }
+}
+
+/// Returns true for the entries GitHubFS writes directly under a cache
+/// directory. Anything else in it is left alone, so a mistyped
+/// `--cache-dir` can't wipe unrelated files.
+fn is_cache_entry(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    name == ETAGS_FILE || name == CONTENTS_DIR || path.extension().is_some_and(|extension| extension == "tmp")
+}
+
+/// Cache entries directly under `dir`.
+fn cache_entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_cache_entry(&path) {
+            entries.push(path);
+        } else {
+            debug!("Ignoring {:?}: not a cache entry", path);
+        }
+    }
+    Ok(entries)
+}
+
+/// Counts the cache files stored under `dir` and their total size.
+pub fn info(dir: &Path) -> io::Result<CacheInfo> {
+    let mut summary = CacheInfo::default();
+    if !dir.exists() {
+        return Ok(summary);
+    }
+    for path in cache_entries(dir)? {
+        count(&path, &mut summary)?;
+    }
+    Ok(summary)
+}
+
+fn count(path: &Path, summary: &mut CacheInfo) -> io::Result<()> {
+    // Don't follow symbolic links out of the cache
+    let metadata = path.symlink_metadata()?;
+    if metadata.is_dir() {
+        scan(path, summary)
+    } else {
+        summary.entries += 1;
+        summary.bytes += metadata.len();
+        Ok(())
+    }
+}
+
+fn scan(dir: &Path, summary: &mut CacheInfo) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        count(&entry?.path(), summary)?;
+    }
+    Ok(())
+}
+
+/// Writes `content` to `path` through a temporary file, so that a crash
+/// never leaves a truncated entry behind.
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
+
+/// Saves the ETags and bodies of conditional requests, keyed by
+/// "accept url", under `dir`.
+pub fn save_etags<'a>(dir: &Path, etags: impl Iterator<Item = (&'a String, &'a EtagResponse)>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let saved: Vec<SavedEtag> = etags
+        .map(|(key, response)| SavedEtag { key: key.clone(), etag: response.etag.clone(), body: BASE64.encode(&response.body) })
+        .collect();
+    let json = serde_json::to_vec(&saved).map_err(io::Error::other)?;
+    write_atomically(&dir.join(ETAGS_FILE), &json)?;
+    debug!("Saved {} ETags to {:?}", saved.len(), dir);
+    Ok(())
+}
+
+/// Loads the ETags saved by `save_etags`, in the order they were saved. A
+/// missing file gives no entries; a corrupt one is ignored with a warning.
+pub fn load_etags(dir: &Path) -> io::Result<Vec<(String, EtagResponse)>> {
+    let path = dir.join(ETAGS_FILE);
+    let json = match fs::read(&path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let saved: Vec<SavedEtag> = match serde_json::from_slice(&json) {
+        Ok(saved) => saved,
+        Err(err) => {
+            warn!("Ignoring unreadable {:?}: {}", path, err);
+            return Ok(Vec::new());
+        }
+    };
+    Ok(saved.into_iter()
+        .filter_map(|entry| Some((entry.key, EtagResponse { etag: entry.etag, body: BASE64.decode(&entry.body).ok()? })))
+        .collect())
+}
+
+/// Returns true for keys that name a git blob, whose content never changes.
+fn is_blob_sha(key: &str) -> bool {
+    key.len() == 40 && key.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Saves file contents keyed by blob SHA under `dir/contents/<variant>`.
+/// Entries keyed by path may be stale on the next run and are skipped.
+pub fn save_contents<'a>(dir: &Path, variant: &str, contents: impl Iterator<Item = (&'a String, &'a Vec<u8>)>) -> io::Result<usize> {
+    let dir = dir.join(CONTENTS_DIR).join(variant);
+    fs::create_dir_all(&dir)?;
+    let mut saved = 0;
+    for (key, content) in contents.filter(|(key, _content)| is_blob_sha(key)) {
+        let path = dir.join(key);
+        if !path.exists() {
+            write_atomically(&path, content)?;
+        }
+        saved += 1;
+    }
+    debug!("Saved {} contents to {:?}", saved, dir);
+    Ok(saved)
+}
+
+/// Loads the contents saved by `save_contents`, as (SHA, content) pairs.
+pub fn load_contents(dir: &Path, variant: &str) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let dir = dir.join(CONTENTS_DIR).join(variant);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut contents = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let key = match path.file_name().and_then(|name| name.to_str()) {
+            Some(key) if is_blob_sha(key) => key.to_string(),
+            _ => continue,
+        };
+        contents.push((key, fs::read(&path)?));
+    }
+    Ok(contents)
+}
+
+/// Removes the saved ETags, contents and leftover temporary files under
+/// `dir`, keeping the directory and anything else in it. Returns the number
+/// of files removed.
+pub fn clear(dir: &Path) -> io::Result<u64> {
+    if !dir.exists() {
+        debug!("Cache directory {:?} does not exist, nothing to clear", dir);
+        return Ok(0);
+    }
+
+    let removed = info(dir)?.entries;
+    for path in cache_entries(dir)? {
+        if path.symlink_metadata()?.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    info!("Removed {} cache entries from {:?}", removed, dir);
+    Ok(removed)
+}
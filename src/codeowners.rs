@@ -0,0 +1,96 @@
+/// Places GitHub looks for a CODEOWNERS file, in order of precedence.
+pub const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+/// Parsed CODEOWNERS file. The last matching rule wins, as on GitHub.
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+impl CodeOwners {
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = normalize_pattern(fields.next()?);
+                let owners = fields
+                    .take_while(|field| !field.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some(Rule { pattern, owners })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Returns the owners of a repo-relative path, or `None` when no rule
+    /// matches or the matching rule lists no owners.
+    pub fn owners_for(&self, path: &str) -> Option<Vec<String>> {
+        let path = path.trim_start_matches('/');
+        let rule = self.rules.iter().rev().find(|rule| {
+            glob_match(rule.pattern.as_bytes(), path.as_bytes())
+                || glob_match(format!("{}/**", rule.pattern).as_bytes(), path.as_bytes())
+        })?;
+        if rule.owners.is_empty() {
+            None
+        } else {
+            Some(rule.owners.clone())
+        }
+    }
+}
+
+/// Turns a CODEOWNERS pattern into a glob anchored at the repo root.
+pub(crate) fn normalize_pattern(pattern: &str) -> String {
+    let trimmed = pattern.trim_end_matches('/');
+    // Patterns without a '/' (other than a trailing one) match at any depth
+    let anchored = pattern.starts_with('/') || trimmed.contains('/');
+    let mut glob = trimmed.trim_start_matches('/').to_string();
+    if pattern.ends_with('/') {
+        glob.push_str("/**");
+    }
+    if !anchored {
+        glob = format!("**/{}", glob);
+    }
+    glob
+}
+
+/// Matches `*` and `?` within a path segment and `**` across segments.
+pub(crate) fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    if pattern.is_empty() {
+        return text.is_empty();
+    }
+    if let Some(rest) = pattern.strip_prefix(b"**") {
+        let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+        if rest.is_empty() || glob_match(rest, text) {
+            return true;
+        }
+        return text
+            .iter()
+            .enumerate()
+            .any(|(i, &c)| c == b'/' && glob_match(rest, &text[i + 1..]));
+    }
+    match pattern[0] {
+        b'*' => {
+            for i in 0..=text.len() {
+                if glob_match(&pattern[1..], &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        b'?' => !text.is_empty() && text[0] != b'/' && glob_match(&pattern[1..], &text[1..]),
+        c => !text.is_empty() && text[0] == c && glob_match(&pattern[1..], &text[1..]),
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the JSON file given with `--config`. Each field
+/// mirrors the flag of the same name; flags given on the command line
+/// override them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub owner: Option<String>,
+    pub org: Option<String>,
+    pub token: Option<String>,
+    pub mountpoint: Option<PathBuf>,
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub api_url: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_size_mb: Option<u64>,
+    pub persist_cache: Option<bool>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("Failed to read config file {:?}: {}", path, err)))?;
+        Self::parse(&text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid config file {:?}: {}", path, err)))
+    }
+
+    pub fn parse(text: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(text)
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// In-memory cache capped by the total size of its values, evicting the
+/// least recently used entries first.
+#[derive(Debug)]
+pub struct ContentCache<K, V> {
+    capacity: u64,
+    size: u64,
+    /// Use counter; the lower, the longer ago the entry was used
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V: AsRef<[u8]> + Clone> ContentCache<K, V> {
+    pub fn new(capacity: u64) -> Self {
+        ContentCache {
+            capacity,
+            size: 0,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a copy of the value and marks it as recently used.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    /// Number of cached values.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Total size of the cached values, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Iterates over the cached entries, least recently used first.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.order.values().filter_map(|key| self.entries.get_key_value(key).map(|(key, (value, _used))| (key, value)))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Stores a value, evicting old entries until it fits. Values larger
+    /// than the whole cache are not stored.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.remove(&key);
+        let len = value.as_ref().len() as u64;
+        if len > self.capacity {
+            return;
+        }
+        while self.size + len > self.capacity {
+            match self.order.pop_first() {
+                Some((_used, oldest)) => {
+                    if let Some((evicted, _used)) = self.entries.remove(&oldest) {
+                        self.size -= evicted.as_ref().len() as u64;
+                    }
+                }
+                None => break,
+            }
+        }
+        self.tick += 1;
+        self.size += len;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some((value, used)) = self.entries.remove(key) {
+            self.order.remove(&used);
+            self.size -= value.as_ref().len() as u64;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.size = 0;
+    }
+}
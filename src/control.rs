@@ -0,0 +1,119 @@
+//! Control socket for a running mount.
+//!
+//! Clients connect to the Unix socket given by `--control-socket` and send
+//! one command per line. Each command gets a single-line reply starting with
+//! `ok` or `error`:
+//!
+//! - `refresh`: drop every cached listing, so each repository is reloaded
+//!   on its next access.
+//! - `stats`: reply with `ok requests=N cache_hits=N cache_misses=N
+//!   not_modified=N`.
+//! - `clear-cache`: drop downloaded file contents on the next access.
+//! - `reload-token`: reread the token from `--token-file`, replying `error`
+//!   when no token file is configured or it can't be read.
+
+use log::{debug, error, info};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use crate::fs::Metrics;
+
+/// Set by `refresh` to reload every repository on the next access.
+pub static REFRESH_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Set by `clear-cache` to drop cached contents on the next access.
+pub static CLEAR_CACHE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// State of the mount that the commands read or replace.
+pub struct Shared {
+    pub metrics: Arc<Metrics>,
+    pub token: Arc<RwLock<Option<String>>>,
+    /// File `reload-token` rereads the token from.
+    pub token_file: Option<PathBuf>,
+}
+
+/// Reads a token from `path`, ignoring surrounding whitespace.
+pub fn read_token_file(path: &Path) -> io::Result<String> {
+    let token = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("Failed to read token file {:?}: {}", path, err)))?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("The token file {:?} is empty", path)));
+    }
+    Ok(token.to_string())
+}
+
+/// Binds the control socket at `path` and serves it on a background thread.
+pub fn spawn(path: &Path, shared: Shared) -> io::Result<()> {
+    // Remove the socket left behind by a previous mount
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    info!("Listening for control commands on {:?}", path);
+
+    thread::Builder::new()
+        .name("control".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = serve(stream, &shared) {
+                            debug!("Control connection closed: {}", err);
+                        }
+                    }
+                    Err(err) => error!("Failed to accept control connection: {}", err),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn serve(stream: UnixStream, shared: &Shared) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let reply = handle(line?.trim(), shared);
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(())
+}
+
+/// Runs one command and returns its reply line.
+fn handle(command: &str, shared: &Shared) -> String {
+    debug!("Control command: {:?}", command);
+    let metrics = &shared.metrics;
+    match command {
+        "refresh" => {
+            REFRESH_REQUESTED.store(true, Ordering::SeqCst);
+            "ok refresh scheduled".to_string()
+        }
+        "stats" => format!(
+            "ok requests={} cache_hits={} cache_misses={} not_modified={}",
+            metrics.requests.load(Ordering::Relaxed),
+            metrics.cache_hits.load(Ordering::Relaxed),
+            metrics.cache_misses.load(Ordering::Relaxed),
+            metrics.not_modified.load(Ordering::Relaxed),
+        ),
+        "clear-cache" => {
+            CLEAR_CACHE_REQUESTED.store(true, Ordering::SeqCst);
+            "ok cache clear scheduled".to_string()
+        }
+        "reload-token" => match &shared.token_file {
+            Some(path) => match read_token_file(path) {
+                Ok(token) => {
+                    *shared.token.write().unwrap() = Some(token);
+                    info!("Reloaded the token from {:?}", path);
+                    "ok token reloaded".to_string()
+                }
+                Err(err) => format!("error {}", err),
+            },
+            None => "error no token file configured".to_string(),
+        },
+        "" => "error empty command".to_string(),
+        other => format!("error unknown command {:?}", other),
+    }
+}
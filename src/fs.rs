@@ -1,354 +1,5897 @@
-use fuser::{FileAttr, FileType, Filesystem, Request, ReplyAttr, ReplyData, ReplyEntry, ReplyDirectory, ReplyXattr};
-use libc::{EINVAL, ENOENT, ENODATA};
-use log::{debug, error, info};
-use reqwest::blocking::Client;
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::ffi::OsStr;
-use std::io::{self, ErrorKind};
-use std::time::{Duration, UNIX_EPOCH};
-use std::os::unix::ffi::OsStrExt;
+use fuser::{
+    FileAttr, FileType, Filesystem, Request, ReplyAttr, ReplyCreate, ReplyData, ReplyEmpty, ReplyEntry, ReplyDirectory,
+    ReplyOpen, ReplyStatfs, ReplyWrite, ReplyXattr,
+};
+use libc::{c_int, EACCES, EAGAIN, EINVAL, EIO, EISDIR, ENODATA, ENOENT, ERANGE, EROFS, ETIMEDOUT};
+use log::{debug, error, info, warn};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::{Certificate, Proxy};
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, ErrorKind, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use fuser::KernelConfig;
 
-const GITHUB_API_URL: &str = "https://api.github.com";
+use crate::cache::{self, EtagResponse};
+use crate::codeowners::{glob_match, CodeOwners, CODEOWNERS_PATHS};
+use crate::content_cache::ContentCache;
+use crate::control;
+use crate::gitattributes::{GitAttributes, GITATTRIBUTES_PATH};
+use crate::lfs::{self, LfsPointer};
+use crate::progress::LoadProgress;
+use crate::transform::{self, LineEndings};
 
-#[derive(Debug, Deserialize)]
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+/// Web host of the public API, for endpoints outside the REST API.
+const GITHUB_WEB_URL: &str = "https://github.com";
+/// Maximum number of redirects followed for a single request.
+const MAX_REDIRECTS: usize = 5;
+/// Block size reported by `statfs`, matching `blksize` in file attributes.
+const STATFS_BLOCK_SIZE: u32 = 512;
+/// Total blocks reported by `statfs` (1 TiB), since the real size of the
+/// remote repositories is unknown.
+const STATFS_TOTAL_BLOCKS: u64 = (1 << 40) / STATFS_BLOCK_SIZE as u64;
+/// Wait before the first retry of a failed request.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// REST API version requested by default through `X-GitHub-Api-Version`.
+pub const DEFAULT_API_VERSION: &str = "2022-11-28";
+/// Media type of JSON API responses.
+const GITHUB_JSON: &str = "application/vnd.github+json";
+/// Default time a failed lookup is remembered, in seconds.
+pub const DEFAULT_NEGATIVE_TTL_SECS: u64 = 5;
+/// Default number of retries after a connection error or a 5xx response.
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+/// Seconds to wait for the rate limit when GitHub doesn't say when it resets.
+const RATE_LIMIT_FALLBACK_WAIT: u64 = 60;
+
+/// Name of the placeholder file shown in place of the entries of a
+/// collapsed directory. It lists the names hidden behind it.
+const DEEP_PLACEHOLDER_NAME: &str = "...";
+/// First inode of the range reserved for synthetic entries. Real entries
+/// are allocated upwards from 2 and never reach it.
+const SYNTHETIC_INODE_BASE: u64 = u64::MAX / 2;
+/// Number of threads used to prefetch file contents.
+const PREFETCH_WORKERS: usize = 4;
+/// Default size limit for prefetched files.
+pub const DEFAULT_PREFETCH_CONTENT_MAX: u64 = 32 * 1024;
+
+/// Files at least this large are read with HTTP range requests, one slice
+/// per `read`, instead of being downloaded whole.
+const RANGE_READ_MIN_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Default limit for the size of a single response body.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 256 * 1024 * 1024;
+/// Default size cap of the in-memory content cache, in megabytes.
+pub const DEFAULT_CACHE_SIZE_MB: u64 = 512;
+
+/// Default number of outstanding background requests the kernel may issue.
+pub const DEFAULT_MAX_BACKGROUND: u16 = 64;
+/// Default number of background requests at which the kernel throttles.
+pub const DEFAULT_CONGESTION_THRESHOLD: u16 = 48;
+
+/// Number of threads used to warm repository roots on mount.
+const WARM_WORKERS: usize = 8;
+/// Warming stops once fewer API requests than this are left in the
+/// current rate limit window.
+const WARM_RATE_LIMIT_RESERVE: u64 = 100;
+
+/// Minimum time between two default-branch checks.
+const DEFAULT_BRANCH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Name of the synthetic per-directory JSON listing.
+const JSON_LISTING_NAME: &str = ".listing.json";
+/// Name of the synthetic per-directory size summary.
+const SIZES_NAME: &str = ".sizes";
+/// File with the repository description, directly under the repository.
+const DESCRIPTION_NAME: &str = ".description";
+/// Hidden file at the mount root describing the running mount.
+const STATUS_NAME: &str = ".githubfs-status";
+/// Marker file shown inside repositories blocked for legal reasons.
+const DMCA_MARKER_NAME: &str = ".dmca";
+/// Marker file shown inside quarantined repositories.
+const ERROR_MARKER_NAME: &str = ".error";
+/// How long a quarantined repository is left alone before retrying.
+const QUARANTINE_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Upper bound for the backoff between refetches of a failing resource.
+const MAX_REFETCH_INTERVAL: Duration = Duration::from_secs(900);
+
+/// Page size requested from paginated endpoints (the API maximum).
+const PER_PAGE: usize = 100;
+/// Safety limit on the number of pages fetched from one endpoint.
+const MAX_PAGES: usize = 1000;
+
+/// Set by the SIGHUP handler to lift every quarantine on the next access.
+pub static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Set by the refresh thread to expire every cached listing on the next
+/// access.
+static LISTINGS_EXPIRED: AtomicBool = AtomicBool::new(false);
+
+/// Synthetic directory with repository metadata files.
+const META_DIR_NAME: &str = ".meta";
+/// File in `.meta` with the conclusion of the latest workflow run.
+const CI_STATUS_NAME: &str = "ci_status";
+/// File in `.meta` with the SPDX identifier of the repository license.
+const LICENSE_NAME: &str = "license";
+/// How long fetched repository metadata is reused.
+const META_TTL: Duration = Duration::from_secs(300);
+
+/// Extended attribute holding the CODEOWNERS owners of a file.
+const XATTR_CODEOWNERS: &str = "user.github.codeowners";
+/// Extended attribute naming the branch a file comes from in union mode.
+const XATTR_BRANCH: &str = "user.github.branch";
+/// Extended attribute holding the blob SHA of a file.
+const XATTR_SHA: &str = "user.github.sha";
+/// Extended attribute holding the repo-relative path of an entry.
+const XATTR_PATH: &str = "user.github.path";
+/// Extended attribute holding the raw download URL of a file.
+const XATTR_DOWNLOAD_URL: &str = "user.github.download_url";
+/// Extended attribute holding the description of a repository, on its root.
+const XATTR_DESCRIPTION: &str = "user.github.description";
+
+/// What to do when two entries would get the same name in one directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollisionStrategy {
+    /// Append the owner (or a number) to the later entry.
+    #[default]
+    Suffix,
+    /// Keep the first entry and drop the others.
+    Skip,
+    /// Abort with an error.
+    Error,
+}
+
+/// Options that tune how the filesystem presents the repositories.
+#[derive(Debug, Clone, Default)]
+pub struct FsOptions {
+    /// Directories at or below this depth (repo root is 0) are listed as a
+    /// single placeholder entry until one of their children is looked up.
+    pub max_depth_display: Option<usize>,
+    /// List the repository README before every other entry of the repo root.
+    pub preopen_readme: bool,
+    /// Expose a `.listing.json` file with the entries of each directory.
+    pub with_json_listing: bool,
+    /// Expose a `.sizes` file with the human-readable size of each entry.
+    pub with_sizes: bool,
+    /// Expose each repository description as a `.description` file.
+    pub with_descriptions: bool,
+    /// Reload a repository when its default branch changes upstream.
+    pub follow_default_branch_changes: bool,
+    /// Expose the CODEOWNERS owners of each file as an extended attribute.
+    pub with_codeowners: bool,
+    /// How to name repositories whose names clash in the mount root.
+    pub collision_strategy: CollisionStrategy,
+    /// Download small files while their directory is loaded.
+    pub prefetch: bool,
+    /// Largest file, in bytes, downloaded by `prefetch`.
+    pub prefetch_content_max: u64,
+    /// Stop retrying a repository after this many consecutive load failures.
+    pub quarantine_after: Option<u32>,
+    /// Extra root certificate (PEM or DER) trusted for HTTPS.
+    pub ca_cert: Option<PathBuf>,
+    /// Skip TLS certificate verification entirely.
+    pub insecure: bool,
+    /// Proxy used for every request, instead of the `HTTPS_PROXY` and
+    /// `HTTP_PROXY` environment variables.
+    pub proxy: Option<String>,
+    /// Ignore the proxy environment variables and connect directly.
+    pub no_proxy: bool,
+    /// Limit on a whole request, from connecting to reading the body.
+    /// `None` keeps reqwest's default.
+    pub timeout: Option<Duration>,
+    /// Limit on establishing the connection.
+    pub connect_timeout: Option<Duration>,
+    /// Times a request is retried after a connection error or a 5xx
+    /// response. 4xx responses are never retried.
+    pub max_retries: u32,
+    /// Line ending style served for text files.
+    pub line_endings: LineEndings,
+    /// Expose the latest GitHub Actions run conclusion as `.meta/ci_status`.
+    pub with_ci: bool,
+    /// Expose the SPDX identifier of the repository license as `.meta/license`.
+    pub with_license: bool,
+    /// Only mount repositories under one of these SPDX licenses. Empty for all.
+    pub licenses: Vec<String>,
+    /// Owner reported for every file and directory.
+    pub uid: u32,
+    /// Group reported for every file and directory.
+    pub gid: u32,
+    /// Stop listing repositories once this many passed the filters.
+    pub max_repos: Option<usize>,
+    /// Only mount repositories matching one of these globs. Empty for all.
+    pub include: Vec<String>,
+    /// Never mount repositories matching one of these globs, even if they
+    /// match an include.
+    pub exclude: Vec<String>,
+    /// Leave out archived repositories.
+    pub no_archived: bool,
+    /// Leave out forks.
+    pub no_forks: bool,
+    /// Mount only private repositories.
+    pub only_private: bool,
+    /// Show only the README of each repository, found with the readme API,
+    /// instead of its tree.
+    pub readme_only: bool,
+    /// Load repository roots in parallel while mounting.
+    pub warm_on_mount: bool,
+    /// Maximum number of repositories warmed by `warm_on_mount`.
+    pub warm_limit: Option<usize>,
+    /// Branches merged into one tree, in order of precedence. Empty for the
+    /// default branch only.
+    pub union_branches: Vec<String>,
+    /// Branch, tag or commit mounted instead of the default branch.
+    pub reference: Option<String>,
+    /// Organization whose repositories are mounted instead of a user's.
+    pub org: Option<String>,
+    /// Base URL of the REST API, for GitHub Enterprise Server. `None` for
+    /// the public API.
+    pub api_url: Option<String>,
+    /// Value of the `X-GitHub-Api-Version` header. `None` omits it and gets
+    /// the server's default version.
+    pub api_version: Option<String>,
+    /// Maximum number of background requests queued by the kernel.
+    pub max_background: u16,
+    /// Number of background requests at which the kernel starts throttling.
+    pub congestion_threshold: u16,
+    /// Serve submodule entries as text with their pinned commit and URL.
+    pub include_submodule_gitlink_content: bool,
+    /// Initial wait before refetching a directory or file that failed. The
+    /// wait doubles with every further failure.
+    pub refetch_interval_on_error: Option<Duration>,
+    /// Serve the first reads of a file through HTTP range requests instead
+    /// of downloading it whole, so `head` and pagers start quickly.
+    pub open_in_pager: bool,
+    /// Leave files with binary extensions out of directory listings.
+    pub hide_binary: bool,
+    /// Also sample file contents to detect binary files (implies `hide_binary`).
+    pub hide_binary_strict: bool,
+    /// Show how many repositories are warmed while mounting.
+    pub startup_progress: bool,
+    /// Mount-relative prefixes (`repo/path`) always fetched fresh, bypassing
+    /// the listing and content caches.
+    pub live_prefixes: Vec<String>,
+    /// Serve the objects behind Git LFS pointer files instead of the pointers.
+    pub resolve_lfs: bool,
+    /// Show the date of the last commit touching an entry as its times,
+    /// looked up on the first `getattr`.
+    pub commit_times: bool,
+    /// Largest response body accepted from the API, in bytes.
+    pub max_response_size: u64,
+    /// Size cap in bytes of everything kept in memory, split by
+    /// `cache_shares` between the downloaded contents, the transformed
+    /// copies kept per inode for reads and the ETag responses.
+    pub cache_size: u64,
+    /// Abort the mount when the rate limit runs out while warming the
+    /// repositories, instead of leaving them to load on first access.
+    pub fail_on_rate_limit: bool,
+    /// Sleep until the rate limit resets and retry, instead of failing the
+    /// request with `EAGAIN`.
+    pub wait_for_ratelimit: bool,
+    /// Interval between `GET /user` pings checking that the token is still valid.
+    pub keepalive_interval: Option<Duration>,
+    /// Directory where ETags are saved on unmount and loaded on mount.
+    /// `None` keeps every cache in memory only.
+    pub persist_dir: Option<PathBuf>,
+    /// Also save downloaded file contents in `persist_dir`.
+    pub persist_contents: bool,
+    /// Interval at which cached listings expire, so that pushed changes
+    /// show up without remounting.
+    pub refresh_interval: Option<Duration>,
+    /// How long a name that wasn't found keeps answering `ENOENT` without
+    /// being looked up again. `None` disables the negative cache.
+    pub negative_ttl: Option<Duration>,
+}
+
+/// Builds the HTTP client, adding the configured root certificate or
+/// disabling certificate checks when asked to. Without `proxy` or
+/// `no_proxy`, reqwest takes the proxy from the environment.
+fn build_client(options: &FsOptions) -> io::Result<Client> {
+    let mut builder = Client::builder().redirect(Policy::none());
+
+    if let Some(path) = &options.ca_cert {
+        let bytes = std::fs::read(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("Failed to read CA certificate {:?}: {}", path, err)))?;
+        let certificate = Certificate::from_pem(&bytes)
+            .or_else(|_| Certificate::from_der(&bytes))
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, format!("Invalid CA certificate {:?}: {}", path, err)))?;
+        info!("Trusting additional CA certificate from {:?}", path);
+        builder = builder.add_root_certificate(certificate);
+    }
+    if options.insecure {
+        warn!("TLS certificate verification is DISABLED (--insecure); use only in test environments");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(url) = &options.proxy {
+        let proxy = Proxy::all(url)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, format!("Invalid proxy URL {}: {}", url, err)))?;
+        info!("Sending requests through proxy {}", url);
+        builder = builder.proxy(proxy);
+    } else if options.no_proxy {
+        info!("Ignoring proxy environment variables (--no-proxy)");
+        builder = builder.no_proxy();
+    }
+
+    builder.build()
+        .map_err(|err| io::Error::other(format!("Failed to build HTTP client: {}", err)))
+}
+
+/// Matches a repository against an `--include`/`--exclude` glob, ignoring
+/// case. Patterns with a `/` match `owner/name`, others just the name.
+fn repo_matches(repo: &GitHubRepository, pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let target = if pattern.contains('/') { &repo.full_name } else { &repo.name };
+    glob_match(pattern.as_bytes(), target.to_lowercase().as_bytes())
+}
+
+/// Keeps the repositories matching an include glob (all of them when there
+/// are none) and no exclude glob.
+fn filter_by_patterns(repos: Vec<GitHubRepository>, include: &[String], exclude: &[String]) -> Vec<GitHubRepository> {
+    if include.is_empty() && exclude.is_empty() {
+        return repos;
+    }
+    repos.into_iter()
+        .filter(|repo| {
+            if let Some(pattern) = exclude.iter().find(|pattern| repo_matches(repo, pattern)) {
+                debug!("Skipping {}: excluded by {:?}", repo.full_name, pattern);
+                return false;
+            }
+            let keep = include.is_empty() || include.iter().any(|pattern| repo_matches(repo, pattern));
+            if !keep {
+                debug!("Skipping {}: not matched by any include", repo.full_name);
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Drops archived repositories, forks or public repositories as the options
+/// ask. Keeps everything by default.
+fn filter_by_flags(repos: Vec<GitHubRepository>, options: &FsOptions) -> Vec<GitHubRepository> {
+    repos.into_iter()
+        .filter(|repo| {
+            let reason = if options.no_archived && repo.archived {
+                "archived"
+            } else if options.no_forks && repo.fork {
+                "a fork"
+            } else if options.only_private && !repo.private {
+                "public"
+            } else {
+                return true;
+            };
+            debug!("Skipping {}: repository is {}", repo.full_name, reason);
+            false
+        })
+        .collect()
+}
+
+/// Keeps the repositories whose detected license is one of `licenses`
+/// (case-insensitive SPDX ids). Repositories without a detected license are
+/// dropped whenever a filter is set.
+fn filter_by_license(repos: Vec<GitHubRepository>, licenses: &[String]) -> Vec<GitHubRepository> {
+    if licenses.is_empty() {
+        return repos;
+    }
+    repos.into_iter()
+        .filter(|repo| {
+            let spdx_id = repo.license.as_ref().and_then(|license| license.spdx_id.as_deref());
+            let keep = spdx_id.is_some_and(|spdx_id| licenses.iter().any(|wanted| wanted.eq_ignore_ascii_case(spdx_id)));
+            if !keep {
+                let name = repo.license.as_ref().and_then(|license| license.name.as_deref());
+                debug!("Skipping {}: license {:?} not selected", repo.full_name, name.or(spdx_id));
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Gives every repository a unique name in the mount root, following
+/// `strategy` for the ones that clash (e.g. `alice/utils` and `acme/utils`).
+fn resolve_name_collisions(repos: Vec<GitHubRepository>, strategy: CollisionStrategy) -> io::Result<Vec<GitHubRepository>> {
+    let mut taken: HashMap<String, String> = HashMap::new();
+    let mut resolved = Vec::with_capacity(repos.len());
+    for mut repo in repos {
+        if let Some(existing) = taken.get(&repo.name) {
+            match strategy {
+                CollisionStrategy::Skip => {
+                    info!("Skipping {}: name already used by {}", repo.full_name, existing);
+                    continue;
+                }
+                CollisionStrategy::Error => {
+                    return Err(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("Name collision between {} and {}", existing, repo.full_name),
+                    ));
+                }
+                CollisionStrategy::Suffix => {
+                    let owner = repo.full_name.split('/').next().unwrap_or_default();
+                    let mut candidate = format!("{}-{}", repo.name, owner);
+                    let mut counter = 2;
+                    while taken.contains_key(&candidate) {
+                        candidate = format!("{}-{}-{}", repo.name, owner, counter);
+                        counter += 1;
+                    }
+                    info!("Showing {} as {} to avoid a name collision", repo.full_name, candidate);
+                    repo.name = candidate;
+                }
+            }
+        }
+        taken.insert(repo.name.clone(), repo.full_name.clone());
+        resolved.push(repo);
+    }
+    Ok(resolved)
+}
+
+/// Returns true for the usual README file names (`README`, `README.md`, ...).
+fn is_readme(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "readme" || name.starts_with("readme.")
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct GitHubRepository {
     name: String,
-    full_name: String,
+    pub(crate) full_name: String,
+    #[serde(default)]
+    default_branch: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    license: Option<RepositoryLicense>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    fork: bool,
+    #[serde(default)]
+    private: bool,
+}
+
+/// License GitHub detected for a repository.
+#[derive(Debug, Deserialize, Clone)]
+struct RepositoryLicense {
+    spdx_id: Option<String>,
+    name: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
-struct GitHubFile {
+pub struct GitHubFile {
     name: String,
-    path: String,
+    pub(crate) path: String,
     #[serde(rename = "type")]
-    file_type: String,
+    pub(crate) file_type: String,
     #[serde(rename = "download_url")]
     download_url: Option<String>,
+    #[serde(default)]
+    pub(crate) size: u64,
+    #[serde(default)]
+    sha: String,
+    /// Target of a symbolic link, when the API reports it
+    #[serde(default)]
+    target: Option<String>,
+    /// Date of the last commit touching the entry, fetched on demand
+    #[serde(skip)]
+    commit_time: Option<SystemTime>,
+    /// Git mode (e.g. `100755`); only the trees API reports it
+    #[serde(default)]
+    git_mode: Option<String>,
+}
+
+impl GitHubFile {
+    /// Kind of inode the entry is shown as.
+    fn kind(&self) -> FileType {
+        match self.file_type.as_str() {
+            "dir" => FileType::Directory,
+            "symlink" => FileType::Symlink,
+            _ => FileType::RegularFile,
+        }
+    }
+
+    /// Git file mode of the entry, as listed or else implied by its type.
+    fn mode(&self) -> &str {
+        if let Some(mode) = &self.git_mode {
+            return mode;
+        }
+        match self.file_type.as_str() {
+            "dir" => "040000",
+            "symlink" => "120000",
+            "submodule" => "160000",
+            _ => "100644",
+        }
+    }
+
+    /// Permission bits matching the git mode: only entries git marks as
+    /// executable get the execute bits.
+    fn perm(&self) -> u16 {
+        match self.mode() {
+            "100755" | "040000" => 0o755,
+            "120000" => 0o777,
+            _ => 0o644,
+        }
+    }
+}
+
+/// Response of the git trees API.
+#[derive(Debug, Deserialize)]
+struct GitTree {
+    tree: Vec<GitTreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitTreeEntry {
+    path: String,
+    mode: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+    sha: String,
+    #[serde(default)]
+    size: u64,
+}
+
+impl GitTreeEntry {
+    /// Converts a tree entry of the directory `dir` into the shape returned
+    /// by the contents API.
+    fn into_file(self, dir: &str) -> GitHubFile {
+        let file_type = match (self.entry_type.as_str(), self.mode.as_str()) {
+            ("tree", _) => "dir",
+            ("commit", _) => "submodule",
+            (_, "120000") => "symlink",
+            _ => "file",
+        };
+        let path = join_path(dir, &self.path);
+        GitHubFile {
+            name: self.path,
+            path,
+            file_type: file_type.to_string(),
+            download_url: None,
+            size: self.size,
+            sha: self.sha,
+            target: None,
+            commit_time: None,
+            git_mode: Some(self.mode),
+        }
+    }
+}
+
+/// Formats a byte count with binary units, e.g. `512 B` or `1.5 MB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// One entry of the `.listing.json` synthetic file.
+#[derive(Debug, Serialize)]
+struct ListingEntry<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    file_type: &'a str,
+    size: u64,
+    sha: &'a str,
+    mode: &'a str,
+}
+
+/// Content of the `.githubfs-status` file.
+#[derive(Debug, Serialize)]
+struct MountStatus<'a> {
+    owner: &'a str,
+    authenticated: bool,
+    /// Owner of the token, when there is one
+    login: Option<&'a str>,
+    api_url: &'a str,
+    repositories: usize,
+    requests: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+    not_modified: u64,
+    cached_files: usize,
+    cached_bytes: u64,
+    /// `None` until a response reports it.
+    rate_limit_remaining: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GitHubFileContent {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    encoding: String,
+    #[serde(rename = "type", default)]
+    content_type: String,
+    #[serde(default)]
+    sha: String,
+    submodule_git_url: Option<String>,
+    target: Option<String>,
+}
+
+/// Entry of the commits API, reduced to the committer date.
+#[derive(Debug, Deserialize)]
+struct CommitSummary {
+    commit: CommitDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitDetails {
+    committer: CommitSignature,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitSignature {
+    date: String,
+}
+
+/// Response of the git blobs API.
+#[derive(Debug, Deserialize)]
+struct GitBlob {
     content: String,
     encoding: String,
 }
 
+impl GitHubFileContent {
+    /// Text served for a submodule entry: the pinned commit and its URL.
+    fn submodule_text(&self) -> String {
+        format!("commit {}\nurl {}\n", self.sha, self.submodule_git_url.as_deref().unwrap_or_default())
+    }
+}
+
+/// Directory entry as handed to `readdir`: inode, kind and name.
+type DirEntry = (u64, FileType, String);
+
+/// The mutable state lives behind locks so callbacks only need `&self` and
+/// can run from several threads; the type is `Send + Sync`.
+///
+/// - Maps read on every callback (`repos`, `files`, `inodes`, `paths`, the
+///   parsed per-repository files) are `RwLock`s, so lookups and listings
+///   proceed concurrently and only loading a listing takes the write lock.
+/// - Caches that are updated on reads (the LRU content caches, backoff and
+///   ETag state) are `Mutex`es.
+/// - A lock is released before another one is taken, so there is no lock
+///   order to get wrong, and no lock is held across an HTTP request.
+/// - Background threads don't touch the state; they raise flags that the
+///   next callback applies (see `apply_control_requests`). The token is the
+///   exception: `reload-token` on the control socket replaces it in place.
 pub struct GitHubFS {
     client: Client,
     username: String,
-    token: String,
-    repos: HashMap<u64, GitHubRepository>,
-    files: HashMap<u64, Vec<GitHubFile>>,
-    next_inode: u64,
+    /// `None` for unauthenticated access to public repositories only.
+    /// Shared with the control socket and the keepalive thread.
+    token: Arc<RwLock<Option<String>>>,
+    /// Login of the token owner, from `GET /user` in `new`
+    login: Option<String>,
+    /// Base URL of the REST API, without a trailing slash
+    api_url: String,
+    repos: RwLock<HashMap<u64, GitHubRepository>>,
+    files: RwLock<HashMap<u64, Vec<GitHubFile>>>,
+    next_inode: AtomicU64,
+    options: FsOptions,
+    /// Collapsed directories opened by a lookup; an entry lives only as
+    /// long as the directory's cached listing
+    expanded: RwLock<HashSet<u64>>,
+    /// (repository inode, path) -> inode, so that each path always keeps
+    /// the same inode
+    inodes: RwLock<HashMap<(u64, String), u64>>,
+    /// File inode -> (repository inode, path in the repository)
+    paths: RwLock<HashMap<u64, (u64, String)>>,
+    /// Inode -> (last kind seen, generation), bumped when the path changes
+    /// kind
+    generations: RwLock<HashMap<u64, (FileType, u64)>>,
+    /// CODEOWNERS already read for each repository (None if there is none)
+    codeowners: RwLock<HashMap<u64, Option<CodeOwners>>>,
+    /// `.gitattributes` already read for each repository (None if there is none)
+    gitattributes: RwLock<HashMap<u64, Option<GitAttributes>>>,
+    /// Repositories without visible content (HTTP 451 or quarantine)
+    markers: RwLock<HashMap<u64, RepoMarker>>,
+    failures: Mutex<HashMap<u64, RepoFailures>>,
+    /// "full_name/path" -> next attempt allowed after failures
+    backoff: Mutex<HashMap<String, RefetchBackoff>>,
+    /// "full_name/path" -> blob SHA, for files already listed
+    shas: RwLock<HashMap<String, String>>,
+    /// Downloaded contents by blob SHA (or "full_name/path" when unknown),
+    /// capped at half of `cache_size`
+    contents: Mutex<ContentCache<String, Vec<u8>>>,
+    /// Downloaded start of files read in parts, by the same key as `contents`
+    prefixes: RwLock<HashMap<String, Vec<u8>>>,
+    /// Inode -> transformed content, reused by partial reads; capped at a
+    /// quarter of `cache_size`
+    served: Mutex<ContentCache<u64, Arc<[u8]>>>,
+    /// (repository or directory, name) -> inode of the synthetic entries
+    synthetic: RwLock<HashMap<(u64, &'static str), u64>>,
+    /// Latest CI status of each repository and when it was fetched
+    ci_status: Mutex<HashMap<u64, (Instant, Option<String>)>>,
+    /// "owner/repo/path" -> branch it came from with `union_branches`
+    origins: RwLock<HashMap<String, String>>,
+    /// (full_name, ref) -> commit SHA, pinned on the first resolution
+    refs: Mutex<HashMap<(String, String), String>>,
+    /// Renamed repositories: old full_name -> new full_name
+    renames: Mutex<HashMap<String, String>>,
+    /// Contents "accept url" -> ETag and body, for conditional requests;
+    /// capped at a quarter of `cache_size`
+    etags: Mutex<ContentCache<String, EtagResponse>>,
+    /// (directory, name) not found -> until when to answer ENOENT directly
+    negative: Mutex<HashMap<(u64, OsString), Instant>>,
+    /// `opendir` handle -> directory entries when it was opened
+    handles: Mutex<HashMap<u64, Vec<DirEntry>>>,
+    next_handle: AtomicU64,
+    last_branch_check: Mutex<Option<Instant>>,
+    /// Last `X-RateLimit-Remaining` value (u64::MAX while unknown)
+    rate_limit_remaining: AtomicU64,
+    metrics: Arc<Metrics>,
 }
 
-impl GitHubFS {
-    pub fn new(username: String, token: String) -> io::Result<Self> {
-        info!("Initializing GitHubFS for user: {}", username);
+/// Counters describing the work done against the GitHub API.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub requests: AtomicU64,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+    /// Conditional requests answered with `304 Not Modified`.
+    pub not_modified: AtomicU64,
+}
 
-        let mut fs = Self {
-            client: Client::new(),
-            username,
-            token,
-            repos: HashMap::new(),
-            files: HashMap::new(),
-            next_inode: 2, // Comeca em 2, pois 1 é reservado para root
-        };
+/// Response of `GET /user`, reduced to the login.
+#[derive(Debug, Deserialize)]
+struct AuthenticatedUser {
+    login: String,
+}
 
-        // Buscar e carregar repositórios durante a inicialização
-        let repos = fs.fetch_repositories()?;
-        let mut repo_inodes = Vec::new();
-        for (index, repo) in repos.into_iter().enumerate() {
-            let inode = index as u64 + 2; // Inode comeca em 2
-            fs.repos.insert(inode, repo);
-            repo_inodes.push(inode);
-        }
+#[derive(Debug, Deserialize)]
+struct RepositoryName {
+    full_name: String,
+}
 
-        // Carrega os arquivos raiz de todos os repositórios
-        for &repo_inode in &repo_inodes {
-            if let Err(err) = fs.load_files(repo_inode, "") {
-                error!("Failed to load root directory files for inode {}: {}", repo_inode, err);
-            }
-        }
+/// Synthetic file shown instead of the content of a repository that can't
+/// be loaded.
+#[derive(Debug, Clone)]
+struct RepoMarker {
+    inode: u64,
+    name: &'static str,
+    message: String,
+}
 
-        info!("Initialized with {} repositories", fs.repos.len());
-        Ok(fs)
+/// Consecutive load failures of one repository.
+#[derive(Debug, Default)]
+struct RepoFailures {
+    count: u32,
+    quarantined_at: Option<Instant>,
+}
+
+/// Refetch backoff of one failing directory or file.
+#[derive(Debug)]
+struct RefetchBackoff {
+    failures: u32,
+    next_attempt: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRuns {
+    #[serde(default)]
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRun {
+    status: Option<String>,
+    conclusion: Option<String>,
+}
+
+impl WorkflowRuns {
+    /// Summarizes the latest run: its conclusion once completed, otherwise
+    /// its status (e.g. `in_progress`).
+    fn status_line(&self) -> Option<String> {
+        let run = self.workflow_runs.first()?;
+        let status = run.conclusion.clone().or_else(|| run.status.clone())?;
+        Some(format!("{}\n", status))
     }
+}
 
-    pub fn fetch_repositories(&self) -> Result<Vec<GitHubRepository>, io::Error> {
-        let api_url = format!("{}/user/repos", GITHUB_API_URL);
-        debug!("Fetching repositories from URL: {}", api_url);
+/// Error payload for `451 Unavailable For Legal Reasons` responses.
+#[derive(Debug)]
+struct UnavailableForLegalReasons(String);
 
-        let response = self.client.get(&api_url)
-            .header("Accept", "application/vnd.github.v3+json")
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("User-Agent", "GitHubFS")
-            .send()
-            .map_err(|err| {
-                error!("Failed to send request to GitHub API: {}", err);
-                io::Error::new(io::ErrorKind::Other, format!("Failed to send request to GitHub API: {}", err))
-            })?;
+impl fmt::Display for UnavailableForLegalReasons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
-        if response.status().is_success() {
-            let repos = response.json::<Vec<GitHubRepository>>()
-                .map_err(|err| {
-                    error!("Failed to parse JSON response: {}", err);
-                    io::Error::new(io::ErrorKind::Other, format!("Failed to parse JSON response: {}", err))
-                })?;
-            debug!("Fetched {} repositories", repos.len());
-            Ok(repos)
-        } else {
-            let status = response.status();
-            let error_message = response.text().unwrap_or_else(|_| "No additional error message".to_string());
-            let full_error_message = format!("GitHub API request failed with status {}: {}", status, error_message);
-            error!("{}", full_error_message);
-            Err(io::Error::new(io::ErrorKind::Other, full_error_message))
-        }
+impl Error for UnavailableForLegalReasons {}
+
+fn is_unavailable_for_legal_reasons(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<UnavailableForLegalReasons>())
+}
+
+/// Error payload of an unsuccessful API response, keeping its status for
+/// `errno_for`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
     }
+}
 
-    fn fetch_file_content(&self, repo_full_name: &str, path: &str) -> Result<Vec<u8>, io::Error> {
-        let api_url = format!("{}/repos/{}/contents/{}", GITHUB_API_URL, repo_full_name, path);
-        debug!("Fetching file content from URL: {}", api_url);
+impl Error for ApiError {}
 
-        let response = self.client.get(&api_url)
-            .header("Accept", "application/vnd.github.v3+json")
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("User-Agent", "GitHubFS")
-            .send()
-            .map_err(|err| {
-                error!("Failed to send request to GitHub API: {}", err);
-                io::Error::new(io::ErrorKind::Other, format!("Failed to send request to GitHub API: {}", err))
-            })?;
+/// Error payload for requests refused because the rate limit is exhausted.
+/// The error kind is `WouldBlock`, which FUSE replies report as `EAGAIN`.
+#[derive(Debug)]
+struct RateLimited(String);
 
-        if response.status().is_success() {
-            let content = response.json::<GitHubFileContent>()
-                .map_err(|err| {
-                    error!("Failed to parse JSON response: {}", err);
-                    io::Error::new(io::ErrorKind::Other, format!("Failed to parse JSON response: {}", err))
-                })?;
-            if content.encoding == "base64" {
-                base64::decode(&content.content)
-                    .map_err(|err| {
-                        error!("Failed to decode base64 content: {}", err);
-                        io::Error::new(io::ErrorKind::Other, format!("Failed to decode base64 content: {}", err))
-                    })
-            } else {
-                error!("Unknown content encoding: {}", content.encoding);
-                Err(io::Error::new(io::ErrorKind::Other, format!("Unknown content encoding: {}", content.encoding)))
-            }
-        } else {
-            let status = response.status();
-            let error_message = response.text().unwrap_or_else(|_| "No additional error message".to_string());
-            let full_error_message = format!("GitHub API request failed with status {}: {}", status, error_message);
-            error!("{}", full_error_message);
-            Err(io::Error::new(io::ErrorKind::Other, full_error_message))
-        }
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
+}
 
-    fn next_inode(&mut self) -> u64 {
-        let inode = self.next_inode;
-        self.next_inode += 1;
-        inode
+impl Error for RateLimited {}
+
+pub fn is_rate_limited(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<RateLimited>())
+}
+
+/// Buffers the body of `response`, failing as soon as it grows beyond
+/// `max_size` bytes instead of reading it all into memory.
+fn limit_body(response: Response, max_size: u64, url: &str) -> io::Result<http::Response<Vec<u8>>> {
+    let too_large = || {
+        let message = format!("Response from {} exceeds the limit of {} bytes", url, max_size);
+        error!("{}", message);
+        io::Error::new(ErrorKind::InvalidData, message)
+    };
+    if response.content_length().is_some_and(|length| length > max_size) {
+        return Err(too_large());
     }
 
-    pub fn load_files(&mut self, repo_id: u64, path: &str) -> io::Result<Vec<GitHubFile>> {
-        let repo = self.repos.get(&repo_id).ok_or_else(|| io::Error::new(ErrorKind::NotFound, "Repository not found"))?;
-        let api_url = format!("{}/repos/{}/contents/{}", GITHUB_API_URL, repo.full_name, path);
-        debug!("Fetching files from URL: {}", api_url);
-    
-        let response = self.client.get(&api_url)
-            .header("Accept", "application/vnd.github.v3+json")
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("User-Agent", "GitHubFS")
-            .send()
-            .map_err(|err| {
-                error!("Failed to send request to GitHub API: {}", err);
-                io::Error::new(io::ErrorKind::Other, format!("Failed to send request to GitHub API: {}", err))
-            })?;
-    
-        if response.status().is_success() {
-            let files = response.json::<Vec<GitHubFile>>()
-                .map_err(|err| {
-                    error!("Failed to parse JSON response: {}", err);
-                    io::Error::new(io::ErrorKind::Other, format!("Failed to parse JSON response: {}", err))
-                })?;
-            
-            debug!("Fetched {} files", files.len());
-    
-            let mut loaded_files = Vec::new();
-    
-            for file in &files {
-                let inode = self.next_inode();
-                loaded_files.push((inode, file.clone()));
-    
-                if file.file_type == "dir" {
-                    let sub_files = self.load_files(repo_id, &file.path)?;
-                    self.files.insert(inode, sub_files);
-                }
-            }
-    
-            self.files.insert(repo_id, files.clone());
-            Ok(loaded_files.into_iter().map(|(_, file)| file).collect())
-        } else {
-            let status = response.status();
-            let error_message = response.text().unwrap_or_else(|_| "No additional error message".to_string());
-            let full_error_message = format!("GitHub API request failed with status {}: {}", status, error_message);
-            error!("{}", full_error_message);
-            Err(io::Error::new(io::ErrorKind::Other, full_error_message))
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(response.headers().iter().map(|(name, value)| (name.clone(), value.clone())));
+    }
+    let mut body = Vec::new();
+    response.take(max_size + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > max_size {
+        return Err(too_large());
+    }
+    builder.body(body)
+        .map_err(|err| io::Error::other(format!("Failed to buffer response from {}: {}", url, err)))
+}
+
+/// Parses an ISO 8601 UTC timestamp as sent by the API, e.g.
+/// `2024-05-01T12:30:00Z`.
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the Gregorian calendar (Howard Hinnant's algorithm)
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(seconds).ok().map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// The part of `content` a `read` at `offset` of up to `size` bytes
+/// returns: empty at or past the end, and cut short when the read runs
+/// over it.
+fn read_chunk(content: &[u8], offset: i64, size: u32) -> &[u8] {
+    let start = usize::try_from(offset).unwrap_or(0).min(content.len());
+    let end = start.saturating_add(size as usize).min(content.len());
+    &content[start..end]
+}
+
+/// Hands the entries from `offset` on to `add` until it reports a full
+/// buffer.
+fn fill_directory(entries: &[DirEntry], offset: i64, mut add: impl FnMut(u64, i64, FileType, &str) -> bool) {
+    // The offset of each entry points at the next one
+    let skip = usize::try_from(offset).unwrap_or(0);
+    for (i, (inode, kind, name)) in entries.iter().enumerate().skip(skip) {
+        if add(*inode, (i + 1) as i64, *kind, name) {
+            break;
         }
     }
-    
-    
+}
 
-    fn attr(&self, ino: u64) -> io::Result<FileAttr> {
-        let kind = if ino == 1 || self.repos.contains_key(&ino) {
-            FileType::Directory
-        } else {
-            FileType::RegularFile
-        };
+/// The filesystem is read-only: opening for writing, creating or truncating
+/// fails with `EROFS`.
+fn check_open_flags(flags: i32) -> Result<(), c_int> {
+    if flags & libc::O_ACCMODE != libc::O_RDONLY || flags & (libc::O_CREAT | libc::O_TRUNC) != 0 {
+        return Err(EROFS);
+    }
+    Ok(())
+}
 
-        Ok(FileAttr {
-            ino,
-            size: 0,
-            blocks: 1,
-            atime: UNIX_EPOCH,
-            mtime: UNIX_EPOCH,
-            ctime: UNIX_EPOCH,
-            crtime: UNIX_EPOCH,
-            kind,
-            perm: 0o755,
-            nlink: 2,
-            uid: 0,
-            gid: 0,
-            rdev: 0,
-            blksize: 512, 
-            flags: 0,
-        })
+/// Answers an xattr request, where `size == 0` asks for the buffer size
+/// needed to hold `value`.
+fn reply_xattr(reply: ReplyXattr, size: u32, value: &[u8]) {
+    if size == 0 {
+        reply.size(value.len() as u32);
+    } else if (size as usize) < value.len() {
+        reply.error(ERANGE);
+    } else {
+        reply.data(value);
     }
 }
 
-impl Filesystem for GitHubFS {
-    fn init(&mut self, _req: &Request<'_>, _config: &mut KernelConfig) -> Result<(), libc::c_int> {
-        info!("GitHubFS initialized");
-    
-        // Verifica se há pelo menos um repositório carregado
-        if let Some((&repo_id, _)) = self.repos.iter().next() {
-            // Carrega os arquivos e diretórios do primeiro repositório carregado
-            if let Err(err) = self.load_files(repo_id, "") {
-                error!("Failed to load root directory files: {}", err);
-            }
-        } else {
-            error!("No repositories loaded");
-            return Err(libc::ENOENT);
-        }
-    
-        Ok(())
+/// Decodes base64 content from the API, which wraps it in lines of 60
+/// characters.
+fn decode_base64(content: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let encoded: String = content.split_ascii_whitespace().collect();
+    BASE64.decode(encoded)
+}
+
+/// Deserializes the JSON body of a successful response.
+fn parse_json<T: DeserializeOwned>(response: Response) -> io::Result<T> {
+    response.json::<T>().map_err(|err| {
+        error!("Failed to parse JSON response: {}", err);
+        io::Error::other(format!("Failed to parse JSON response: {}", err))
+    })
+}
+
+/// Parses a contents API response for a file. A directory path answers
+/// with an array of entries instead, reported as `IsADirectory`.
+fn parse_file_content(body: &[u8], repo_full_name: &str, path: &str) -> io::Result<GitHubFileContent> {
+    if body.trim_ascii_start().starts_with(b"[") {
+        error!("{}/{} is a directory, not a file", repo_full_name, path);
+        return Err(io::Error::new(ErrorKind::IsADirectory, format!("{}/{} is a directory", repo_full_name, path)));
     }
-    
+    serde_json::from_slice(body).map_err(|err| {
+        error!("Failed to parse JSON response: {}", err);
+        io::Error::other(format!("Failed to parse JSON response: {}", err))
+    })
+}
 
-    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
-        debug!("lookup(parent: {}, name: {:?})", parent, name);
+/// Value of the `X-RateLimit-Remaining` header, when present.
+fn rate_limit_remaining(response: &Response) -> Option<u64> {
+    response.headers()
+        .get("X-RateLimit-Remaining")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
 
-        if parent == 1 {
-            // Diretório raiz, procura pelo repositórios
-            if let Some((&inode, _repo)) = self.repos.iter().find(|(_inode, repo)| OsStr::new(&repo.name) == name) {
-                reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), 0);
-                return;
-            }
-        } else {
-            // Procura arquivos em repositórios
-            if let Some(files) = self.files.get(&parent) {
-                for file in files {
-                    if OsStr::new(&file.name) == name {
-                        let inode = self.next_inode();
-                        reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), 0);
-                        return;
-                    }
-                }
-            }
-        }
+/// Builds and logs the error for an unsuccessful API response.
+fn api_error(response: Response) -> io::Error {
+    let status = response.status();
+    let error_message = response.text().unwrap_or_else(|_| "No additional error message".to_string());
+    status_error(status, &error_message)
+}
 
-        reply.error(ENOENT);
+fn status_error(status: StatusCode, error_message: &str) -> io::Error {
+    if status == StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS {
+        let message = format!("Unavailable for legal reasons (HTTP 451): {}", error_message);
+        error!("{}", message);
+        return io::Error::new(ErrorKind::PermissionDenied, UnavailableForLegalReasons(message));
     }
+    let full_error_message = format!("GitHub API request failed with status {}: {}", status, error_message);
+    error!("{}", full_error_message);
+    let kind = match status {
+        StatusCode::NOT_FOUND => ErrorKind::NotFound,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Other,
+    };
+    io::Error::new(kind, ApiError { status, message: full_error_message })
+}
 
-    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
-        debug!("getattr(ino: {})", ino);
+/// errno matching the status of an unsuccessful API response.
+fn http_status_to_errno(status: StatusCode) -> c_int {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => EACCES,
+        StatusCode::NOT_FOUND | StatusCode::GONE => ENOENT,
+        StatusCode::TOO_MANY_REQUESTS => EAGAIN,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ETIMEDOUT,
+        _ => EIO,
+    }
+}
 
-        match self.attr(ino) {
-            Ok(attr) => reply.attr(&Duration::new(1, 0), &attr),
-            Err(_) => reply.error(ENOENT),
-        }
+/// Status of the API response behind an error, if it came from one.
+fn api_status(err: &io::Error) -> Option<StatusCode> {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<ApiError>())
+        .map(|api| api.status)
+}
+
+/// errno reported to the kernel for a failed operation. A rate-limited
+/// 403 becomes `EAGAIN`, not `EACCES`.
+fn errno_for(err: &io::Error) -> c_int {
+    if is_rate_limited(err) {
+        return EAGAIN;
+    }
+    if is_unavailable_for_legal_reasons(err) {
+        return EACCES;
+    }
+    if let Some(status) = api_status(err) {
+        return http_status_to_errno(status);
     }
+    match err.kind() {
+        ErrorKind::NotFound => ENOENT,
+        ErrorKind::PermissionDenied => EACCES,
+        ErrorKind::TimedOut => ETIMEDOUT,
+        ErrorKind::WouldBlock => EAGAIN,
+        ErrorKind::IsADirectory => EISDIR,
+        _ => EIO,
+    }
+}
 
-    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
-        debug!("readdir(ino: {}, offset: {})", ino, offset);
-    
-        if offset != 0 {
-            reply.ok();
-            return;
+/// What one keepalive ping found out about the token.
+#[derive(Debug, PartialEq)]
+enum TokenCheck {
+    /// The token works and has these `X-OAuth-Scopes`.
+    Valid(Option<String>),
+    /// GitHub rejected the token with a 401.
+    Revoked,
+    /// The ping failed for another reason; the next one may succeed.
+    Unknown,
+}
+
+/// Pings `GET /user` once, warning when GitHub rejects the token.
+fn check_token(client: &Client, api_url: &str, api_version: Option<&str>, token: &str) -> TokenCheck {
+    let mut request = client.get(format!("{}/user", api_url))
+        .header("Accept", GITHUB_JSON)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "GitHubFS");
+    if let Some(version) = api_version {
+        request = request.header("X-GitHub-Api-Version", version);
+    }
+    let response = match request.send() {
+        Ok(response) => response,
+        Err(err) => {
+            debug!("Keepalive request failed: {}", err);
+            return TokenCheck::Unknown;
         }
-    
-        reply.add(ino, 1, FileType::Directory, ".");
-        reply.add(ino, 2, FileType::Directory, "..");
-    
-        if ino == 1 {
-            for (inode, repo) in &self.repos {
-                reply.add(*inode, *inode as i64, FileType::Directory, &repo.name);
-            }
-        } else if let Some(files) = self.files.get(&ino) {
-            // Cria uma cópia dos arquivos para evitar problemas de mutabilidade
-            let files = files.clone();
-    
-            // Itera sobre os arquivos sem a necessidade de mutar self.files diretamente
-            for (i, file) in files.iter().enumerate() {
-                let kind = if file.file_type == "dir" { FileType::Directory } else { FileType::RegularFile };
-                let inode = self.next_inode();
-    
-                // Insere o arquivo no HashMap usando uma nova entrada de vetor
-                self.files.insert(inode, vec![file.clone()]);
-    
-                // Adiciona a entrada ao reply
-                reply.add(inode, (i + 3) as i64, kind, &file.name);
-            }
-        }
-    
-        reply.ok();
+    };
+    if response.status() == StatusCode::UNAUTHORIZED {
+        error!("GitHub rejected the token (HTTP 401): it was revoked or expired; further reads will fail until the filesystem is remounted with a valid token");
+        return TokenCheck::Revoked;
+    }
+    if !response.status().is_success() {
+        warn!("Keepalive check returned HTTP {}", response.status());
+        return TokenCheck::Unknown;
+    }
+    let scopes = response.headers()
+        .get("X-OAuth-Scopes")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    TokenCheck::Valid(scopes)
+}
+
+/// Pings `GET /user` every `interval` on a background thread, warning as
+/// soon as the token is revoked or its scopes change.
+fn spawn_keepalive(client: Client, api_url: String, api_version: Option<String>, token: Arc<RwLock<Option<String>>>, interval: Duration) {
+    let spawned = thread::Builder::new()
+        .name("keepalive".to_string())
+        .spawn(move || {
+            let mut scopes: Option<String> = None;
+            loop {
+                thread::sleep(interval);
+                let Some(current_token) = token.read().unwrap().clone() else {
+                    continue;
+                };
+                let TokenCheck::Valid(current) = check_token(&client, &api_url, api_version.as_deref(), &current_token) else {
+                    continue;
+                };
+                if scopes.is_some() && scopes != current {
+                    warn!("Token scopes changed from {:?} to {:?}", scopes.as_deref().unwrap_or_default(), current.as_deref().unwrap_or_default());
+                }
+                scopes = current;
+                debug!("Keepalive check succeeded");
+            }
+        });
+    if let Err(err) = spawned {
+        error!("Failed to start keepalive thread: {}", err);
+    }
+}
+
+/// Expires the cached listings every `interval` from a background thread.
+/// The listings are dropped on the next filesystem access and fetched again
+/// when used, with ETags sparing the unchanged ones.
+fn spawn_refresh(interval: Duration) {
+    let spawned = thread::Builder::new()
+        .name("refresh".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            debug!("Expiring cached listings");
+            LISTINGS_EXPIRED.store(true, Ordering::SeqCst);
+        });
+    if let Err(err) = spawned {
+        error!("Failed to start refresh thread: {}", err);
+    }
+}
+
+/// Preferred inode of the synthetic entry `name` of `owner`, derived from
+/// the key alone (FNV-1a) so it is the same on every mount.
+fn synthetic_inode_for(owner: u64, name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in owner.to_le_bytes().iter().chain(name.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    SYNTHETIC_INODE_BASE + hash % (u64::MAX - SYNTHETIC_INODE_BASE)
+}
+
+/// Splits `cache_size` between the in-memory caches: half for downloaded
+/// contents, a quarter each for transformed copies and ETag responses.
+fn cache_shares(cache_size: u64) -> (u64, u64, u64) {
+    let contents = cache_size / 2;
+    let served = cache_size / 4;
+    (contents, served, cache_size - contents - served)
+}
+
+/// Inode tried after `inode` when it is taken, wrapping around the
+/// synthetic range.
+fn next_synthetic_inode(inode: u64) -> u64 {
+    if inode == u64::MAX { SYNTHETIC_INODE_BASE } else { inode + 1 }
+}
+
+/// Canonical form of a repo-relative path: segments joined by single
+/// slashes, without leading or trailing ones (`/a//b/` becomes `a/b`).
+fn normalize_path(path: &str) -> String {
+    path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>().join("/")
+}
+
+/// Joins a directory and an entry name into a canonical path.
+fn join_path(dir: &str, name: &str) -> String {
+    normalize_path(&format!("{}/{}", dir, name))
+}
+
+/// Returns the `rel="next"` URL of a `Link` header.
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        if !params.split(';').any(|param| param.trim() == "rel=\"next\"") {
+            return None;
+        }
+        Some(url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+/// Wait before retry number `retry` (from 1): doubles each time from
+/// `RETRY_BASE_DELAY`, plus up to 50% of jitter so that parallel requests
+/// don't retry in lockstep.
+fn retry_delay(retry: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY.saturating_mul(1 << (retry - 1).min(10));
+    // No rand dependency: the clock's nanoseconds are noise enough
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+/// Drops trailing slashes from an API base URL, so that
+/// `https://ghe.corp/api/v3/` and `https://ghe.corp/api/v3` build the same URLs.
+fn normalize_api_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Adds `?ref=` to an API URL when `git_ref` is given. The ref is encoded,
+/// since branch and tag names may contain `#`, `+` or `&`.
+fn with_ref(url: String, git_ref: Option<&str>) -> io::Result<String> {
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref,
+        None => return Ok(url),
+    };
+    let mut parsed = reqwest::Url::parse(&url)
+        .map_err(|err| io::Error::other(format!("Invalid API URL {}: {}", url, err)))?;
+    parsed.query_pairs_mut().append_pair("ref", git_ref);
+    Ok(parsed.into())
+}
+
+/// Returns true when two URLs share scheme, host and port, so that
+/// credentials meant for one may be sent to the other.
+fn same_origin(url: &str, other: &str) -> bool {
+    match (reqwest::Url::parse(url), reqwest::Url::parse(other)) {
+        (Ok(url), Ok(other)) => url.origin() == other.origin(),
+        _ => false,
+    }
+}
+
+/// The `KernelConfig` setters used by `init`, so that the tuning can be
+/// checked without a kernel.
+trait KernelTuning {
+    fn add_capabilities(&mut self, capabilities: u32) -> Result<(), u32>;
+    fn set_max_background(&mut self, value: u16) -> Result<u16, u16>;
+    fn set_congestion_threshold(&mut self, value: u16) -> Result<u16, u16>;
+}
+
+impl KernelTuning for KernelConfig {
+    fn add_capabilities(&mut self, capabilities: u32) -> Result<(), u32> {
+        KernelConfig::add_capabilities(self, capabilities)
+    }
+
+    fn set_max_background(&mut self, value: u16) -> Result<u16, u16> {
+        KernelConfig::set_max_background(self, value)
+    }
+
+    fn set_congestion_threshold(&mut self, value: u16) -> Result<u16, u16> {
+        KernelConfig::set_congestion_threshold(self, value)
+    }
+}
+
+/// Extracts the `owner/name` part of a `/repos/{owner}/{name}/...` URL.
+fn repo_full_name_from_url(url: &str) -> Option<String> {
+    let rest = &url[url.find("/repos/")? + "/repos/".len()..];
+    let mut segments = rest.split(['/', '?']);
+    let owner = segments.next().filter(|s| !s.is_empty())?;
+    let name = segments.next().filter(|s| !s.is_empty())?;
+    Some(format!("{}/{}", owner, name))
+}
+
+impl GitHubFS {
+    pub fn new(username: String, token: Option<String>, options: FsOptions) -> io::Result<Self> {
+        info!("Initializing GitHubFS for user: {}", username);
+
+        let mut fs = Self::unloaded(username, token, options)?;
+        fs.login = fs.verify_token()?;
+        fs.load_persisted_cache();
+
+        let repos = fs.fetch_repositories()?;
+        let repos = resolve_name_collisions(repos, fs.options.collision_strategy)?;
+        let mut repo_inodes = Vec::new();
+        for repo in repos {
+            repo_inodes.push(fs.add_repository(repo));
+        }
+
+        // Without warming, each root is loaded on its first lookup or readdir
+        if fs.options.warm_on_mount {
+            fs.warm_repo_roots(&repo_inodes)?;
+        }
+
+        info!("Initialized with {} repositories", fs.repos.read().unwrap().len());
+        Ok(fs)
     }
-    
-    fn read(
-        &mut self,
-        _req: &Request<'_>,
-        ino: u64,
-        _fh: u64,
-        offset: i64,
-        size: u32,
-        _flags: i32,
-        _lock_owner: Option<u64>,
-        reply: ReplyData,
-    ) {
-        debug!("read(ino: {}, offset: {}, size: {})", ino, offset, size);
 
-        for files in self.files.values() {
-            if let Some(file) = files.iter().find(|file| file.path == ino.to_string()) {
-                if let Some(ref download_url) = file.download_url {
-                    match self.fetch_file_content(&file.path, &file.path) {
-                        Ok(content) => {
-                            let data = &content[offset as usize..std::cmp::min(content.len(), (offset + size as i64) as usize)];
-                            reply.data(data);
+    /// Loads the root of up to `warm_limit` repositories in parallel,
+    /// advancing the startup progress once per repository, and returns how
+    /// many were tried. The others are loaded on their first `readdir`.
+    /// Failures are logged, except for an exhausted rate limit under
+    /// `fail_on_rate_limit`, which aborts.
+    fn warm_repo_roots(&self, repo_inodes: &[u64]) -> io::Result<usize> {
+        let limit = self.options.warm_limit.unwrap_or(repo_inodes.len()).min(repo_inodes.len());
+        let targets = &repo_inodes[..limit];
+        let started = Instant::now();
+        info!("Warming {} of {} repositories", targets.len(), repo_inodes.len());
+
+        let next = AtomicUsize::new(0);
+        let warmed = AtomicUsize::new(0);
+        let aborted = Mutex::new(None);
+        let progress = LoadProgress::new(targets.len(), self.options.startup_progress);
+        thread::scope(|scope| {
+            for _ in 0..WARM_WORKERS.min(targets.len()) {
+                scope.spawn(|| {
+                    while let Some(&repo_inode) = targets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if aborted.lock().unwrap().is_some() {
+                            break;
+                        }
+                        if self.rate_limit_remaining.load(Ordering::Relaxed) < WARM_RATE_LIMIT_RESERVE {
+                            warn!("Stopping warmup: GitHub API rate limit almost exhausted");
+                            break;
                         }
-                        Err(err) => {
-                            error!("Failed to fetch file content: {}", err);
-                            reply.error(ENOENT);
+                        let result = self.load_repo_root(repo_inode);
+                        progress.advance();
+                        match result {
+                            Ok(()) => {
+                                warmed.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(err) if self.options.fail_on_rate_limit && is_rate_limited(&err) => {
+                                *aborted.lock().unwrap() = Some(err);
+                                break;
+                            }
+                            Err(err) => error!("Failed to warm repository {}: {}", repo_inode, err),
                         }
                     }
-                } else {
-                    reply.error(ENOENT);
+                });
+            }
+        });
+        if let Some(err) = aborted.into_inner().unwrap() {
+            return Err(err);
+        }
+        info!("Warmed {} repositories in {:?}", warmed.into_inner(), started.elapsed());
+        Ok(progress.loaded())
+    }
+
+    /// Creates the filesystem without fetching anything from GitHub.
+    pub(crate) fn unloaded(username: String, token: Option<String>, options: FsOptions) -> io::Result<Self> {
+        let (contents_size, served_size, etags_size) = cache_shares(options.cache_size);
+        let api_url = normalize_api_url(options.api_url.as_deref().unwrap_or(GITHUB_API_URL));
+        Ok(Self {
+            client: build_client(&options)?,
+            username,
+            token: Arc::new(RwLock::new(token)),
+            login: None,
+            api_url,
+            repos: RwLock::new(HashMap::new()),
+            files: RwLock::new(HashMap::new()),
+            next_inode: AtomicU64::new(2), // Comeca em 2, pois 1 é reservado para root
+            options,
+            expanded: RwLock::new(HashSet::new()),
+            inodes: RwLock::new(HashMap::new()),
+            paths: RwLock::new(HashMap::new()),
+            generations: RwLock::new(HashMap::new()),
+            codeowners: RwLock::new(HashMap::new()),
+            gitattributes: RwLock::new(HashMap::new()),
+            markers: RwLock::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+            backoff: Mutex::new(HashMap::new()),
+            shas: RwLock::new(HashMap::new()),
+            contents: Mutex::new(ContentCache::new(contents_size)),
+            prefixes: RwLock::new(HashMap::new()),
+            served: Mutex::new(ContentCache::new(served_size)),
+            synthetic: RwLock::new(HashMap::new()),
+            ci_status: Mutex::new(HashMap::new()),
+            origins: RwLock::new(HashMap::new()),
+            refs: Mutex::new(HashMap::new()),
+            renames: Mutex::new(HashMap::new()),
+            etags: Mutex::new(ContentCache::new(etags_size)),
+            negative: Mutex::new(HashMap::new()),
+            handles: Mutex::new(HashMap::new()),
+            // 0 is left for reads without opendir
+            next_handle: AtomicU64::new(1),
+            last_branch_check: Mutex::new(None),
+            rate_limit_remaining: AtomicU64::new(u64::MAX),
+            metrics: Arc::default(),
+        })
+    }
+
+    /// Subdirectory for saved contents: with `--resolve-lfs` a blob holds
+    /// the LFS object instead of the pointer.
+    fn contents_variant(&self) -> &'static str {
+        if self.options.resolve_lfs { "lfs" } else { "git" }
+    }
+
+    /// Loads the ETags (and contents, if enabled) saved by a previous mount.
+    /// Saved responses are only served after a `304` confirms them.
+    fn load_persisted_cache(&self) {
+        let dir = match &self.options.persist_dir {
+            Some(dir) => dir,
+            None => return,
+        };
+        match cache::load_etags(dir) {
+            Ok(etags) => {
+                info!("Loaded {} saved ETags from {:?}", etags.len(), dir);
+                let mut cache = self.etags.lock().unwrap();
+                for (key, response) in etags {
+                    cache.insert(key, response);
+                }
+            }
+            Err(err) => warn!("Failed to load saved ETags from {:?}: {}", dir, err),
+        }
+        if self.options.persist_contents {
+            match cache::load_contents(dir, self.contents_variant()) {
+                Ok(contents) => {
+                    info!("Loaded {} saved file contents from {:?}", contents.len(), dir);
+                    let mut cache = self.contents.lock().unwrap();
+                    for (sha, content) in contents {
+                        cache.insert(sha, content);
+                    }
+                }
+                Err(err) => warn!("Failed to load saved contents from {:?}: {}", dir, err),
+            }
+        }
+    }
+
+    /// Saves the ETags (and contents, if enabled) for the next mount.
+    fn persist_cache(&self) {
+        let dir = match &self.options.persist_dir {
+            Some(dir) => dir,
+            None => return,
+        };
+        if let Err(err) = cache::save_etags(dir, self.etags.lock().unwrap().iter()) {
+            error!("Failed to save ETags to {:?}: {}", dir, err);
+        }
+        if self.options.persist_contents {
+            let contents = self.contents.lock().unwrap();
+            match cache::save_contents(dir, self.contents_variant(), contents.iter()) {
+                Ok(saved) => info!("Saved {} file contents to {:?}", saved, dir),
+                Err(err) => error!("Failed to save contents to {:?}: {}", dir, err),
+            }
+        }
+    }
+
+    /// Adds a repository under the mount root and returns its inode.
+    pub(crate) fn add_repository(&self, repo: GitHubRepository) -> u64 {
+        let inode = self.next_inode();
+        self.repos.write().unwrap().insert(inode, repo);
+        inode
+    }
+
+    pub(crate) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Handle on the metrics for threads outside the filesystem.
+    pub(crate) fn shared_metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Handle on the token, which the control socket may replace.
+    pub(crate) fn shared_token(&self) -> Arc<RwLock<Option<String>>> {
+        Arc::clone(&self.token)
+    }
+
+    fn is_authenticated(&self) -> bool {
+        self.token.read().unwrap().is_some()
+    }
+
+    /// Lets the kernel issue concurrent reads, since every request waits on
+    /// the network.
+    fn tune_kernel_config(&self, config: &mut impl KernelTuning) {
+        if let Err(missing) = config.add_capabilities(fuser::consts::FUSE_ASYNC_READ) {
+            warn!("Kernel does not support asynchronous reads (missing capabilities {:#x})", missing);
+        }
+        if let Err(nearest) = config.set_max_background(self.options.max_background) {
+            warn!("max_background {} rejected by the kernel, using {}", self.options.max_background, nearest);
+            let _ = config.set_max_background(nearest);
+        }
+        if let Err(nearest) = config.set_congestion_threshold(self.options.congestion_threshold) {
+            warn!("congestion_threshold {} rejected by the kernel, using {}", self.options.congestion_threshold, nearest);
+            let _ = config.set_congestion_threshold(nearest);
+        }
+        debug!(
+            "Kernel config: max_background={}, congestion_threshold={}",
+            self.options.max_background, self.options.congestion_threshold
+        );
+    }
+
+    /// Applies the requests queued through the control socket.
+    fn apply_control_requests(&self) {
+        if control::CLEAR_CACHE_REQUESTED.swap(false, Ordering::SeqCst) {
+            info!("Clearing content cache");
+            self.contents.lock().unwrap().clear();
+            self.prefixes.write().unwrap().clear();
+            self.served.lock().unwrap().clear();
+            self.etags.lock().unwrap().clear();
+            self.negative.lock().unwrap().clear();
+        }
+        if control::REFRESH_REQUESTED.swap(false, Ordering::SeqCst) {
+            // Each repository is reloaded on its next access, not here
+            info!("Refreshing all repositories");
+            self.ci_status.lock().unwrap().clear();
+            self.expire_listings();
+        }
+        if LISTINGS_EXPIRED.swap(false, Ordering::SeqCst) {
+            self.expire_listings();
+        }
+        if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+            self.lift_quarantines();
+        }
+    }
+
+    /// Ends every quarantine and unpins every ref, so that the next access
+    /// of each repository loads it again at the current state of its branch.
+    fn lift_quarantines(&self) {
+        info!("Reload requested, lifting all quarantines");
+        self.failures.lock().unwrap().clear();
+        self.markers.write().unwrap().retain(|_repo, marker| marker.name != ERROR_MARKER_NAME);
+        self.unpin_refs(None);
+    }
+
+    /// Drops every cached listing and pinned ref, so that the next access
+    /// of each directory sees the current state of its branch.
+    fn expire_listings(&self) {
+        debug!("Cached listings expired");
+        self.served.lock().unwrap().clear();
+        self.unpin_refs(None);
+        self.negative.lock().unwrap().clear();
+        let repo_inodes: Vec<u64> = self.repos.read().unwrap().keys().copied().collect();
+        for repo_id in repo_inodes {
+            self.forget_listings(repo_id);
+        }
+    }
+
+    /// GETs `url` and deserializes the JSON body, turning unsuccessful
+    /// responses into errors.
+    fn get_json<T: DeserializeOwned>(&self, url: &str) -> io::Result<T> {
+        let response = self.send_request(url)?;
+        if !response.status().is_success() {
+            return Err(api_error(response));
+        }
+        parse_json(response)
+    }
+
+    /// GETs `url` with the given media type and returns the raw body.
+    fn get_bytes(&self, url: &str, accept: &str) -> io::Result<Vec<u8>> {
+        let response = self.send_request_with_accept(url, accept)?;
+        if !response.status().is_success() {
+            return Err(api_error(response));
+        }
+        response.bytes()
+            .map(|bytes| bytes.to_vec())
+            .map_err(|err| {
+                error!("Failed to read response body from {}: {}", url, err);
+                io::Error::other(format!("Failed to read response body: {}", err))
+            })
+    }
+
+    /// Sends a GET request for the API's JSON media type.
+    fn send_request(&self, url: &str) -> io::Result<Response> {
+        self.send_request_with_accept(url, GITHUB_JSON)
+    }
+
+    /// Sends a GET request for the media type `accept`, e.g. the raw or SHA
+    /// form of a resource.
+    fn send_request_with_accept(&self, url: &str, accept: &str) -> io::Result<Response> {
+        self.send_request_with_range(url, accept, None)
+    }
+
+    /// Sends `request`, logging it at debug level under a sequential request
+    /// ID with its status, duration and the remaining rate limit.
+    fn send_traced(&self, method: &str, url: &str, request: RequestBuilder) -> reqwest::Result<Response> {
+        let id = self.metrics.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        let started = Instant::now();
+        let result = request.send();
+        let duration_ms = started.elapsed().as_millis();
+        match &result {
+            Ok(response) => debug!(
+                "request id={} method={} url={} status={} duration_ms={} ratelimit_remaining={}",
+                id, method, url, response.status().as_u16(), duration_ms,
+                rate_limit_remaining(response).map_or("-".to_string(), |remaining| remaining.to_string()),
+            ),
+            Err(err) => debug!(
+                "request id={} method={} url={} error=\"{}\" duration_ms={}",
+                id, method, url, err, duration_ms,
+            ),
+        }
+        result
+    }
+
+    /// Sends a GET request, asking only for the bytes `start..end` when
+    /// `range` is given. This is where every API request goes through:
+    ///
+    /// - Redirects are followed by hand, so that a renamed repository (`301`
+    ///   to a new location) is recorded and later requests go straight to
+    ///   the new name. The token is only sent to the API host.
+    /// - Connection errors and 5xx responses are retried up to
+    ///   `max_retries` times with backoff; an exhausted rate limit fails
+    ///   with `WouldBlock`, or waits for the reset with `wait_for_ratelimit`.
+    /// - Contents requests are revalidated with the ETag of their cached
+    ///   body, which a `304` reuses.
+    fn send_request_with_range(&self, url: &str, accept: &str, range: Option<(u64, u64)>) -> io::Result<Response> {
+        let mut url = url.to_string();
+        let mut redirects = 0;
+        let mut retries = 0;
+        loop {
+            let mut request = self.client.get(&url)
+                .header("Accept", accept)
+                .header("User-Agent", "GitHubFS");
+            // A redirect may lead to another host, which must not see the token
+            let token = self.token.read().unwrap().clone();
+            if let (Some(token), true) = (token, same_origin(&url, &self.api_url)) {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+            if let Some(version) = &self.options.api_version {
+                request = request.header("X-GitHub-Api-Version", version);
+            }
+            // Only the contents endpoint uses ETags; partial reads never do
+            let etag_key = (range.is_none() && url.contains("/contents/")).then(|| format!("{} {}", accept, url));
+            if let Some(key) = &etag_key {
+                if let Some(cached) = self.etags.lock().unwrap().get(key) {
+                    request = request.header("If-None-Match", cached.etag);
+                }
+            }
+            if let Some((start, end)) = range {
+                request = request.header("Range", format!("bytes={}-{}", start, end - 1));
+            }
+            let response = match self.send_traced("GET", &url, request) {
+                Ok(response) if response.status().is_server_error() && retries < self.options.max_retries => {
+                    retries += 1;
+                    let delay = retry_delay(retries);
+                    warn!("{} answered HTTP {}, retrying in {:?} ({}/{})", url, response.status(), delay, retries, self.options.max_retries);
+                    thread::sleep(delay);
+                    continue;
+                }
+                Ok(response) => response,
+                Err(err) if err.is_connect() && retries < self.options.max_retries => {
+                    retries += 1;
+                    let delay = retry_delay(retries);
+                    warn!("Failed to connect for {}: {}; retrying in {:?} ({}/{})", url, err, delay, retries, self.options.max_retries);
+                    thread::sleep(delay);
+                    continue;
+                }
+                Err(err) => {
+                    error!("Failed to send request to GitHub API: {}", err);
+                    let kind = if err.is_timeout() { ErrorKind::TimedOut } else { ErrorKind::Other };
+                    return Err(io::Error::new(kind, format!("Failed to send request to GitHub API: {}", err)));
+                }
+            };
+
+            let remaining = rate_limit_remaining(&response);
+            if let Some(remaining) = remaining {
+                self.rate_limit_remaining.store(remaining, Ordering::Relaxed);
+            }
+            let limited = matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS);
+            if limited && remaining == Some(0) {
+                let reset = response.headers()
+                    .get("X-RateLimit-Reset")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok());
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                let wait = reset.map_or(RATE_LIMIT_FALLBACK_WAIT, |reset| reset.saturating_sub(now) + 1);
+                let message = format!(
+                    "GitHub API rate limit exhausted (resets at {}, in {}s) for {}",
+                    reset.map_or("unknown".to_string(), |reset| reset.to_string()), wait, url,
+                );
+                if self.options.wait_for_ratelimit {
+                    warn!("{}; waiting for the reset", message);
+                    thread::sleep(Duration::from_secs(wait));
+                    continue;
+                }
+                warn!("{}", message);
+                return Err(io::Error::new(ErrorKind::WouldBlock, RateLimited(message)));
+            }
+
+            if response.status() == StatusCode::NOT_MODIFIED {
+                let cached = etag_key.as_ref().and_then(|key| self.etags.lock().unwrap().get(key));
+                if let Some(cached) = cached {
+                    debug!("{} not modified, reusing the cached response", url);
+                    self.metrics.not_modified.fetch_add(1, Ordering::Relaxed);
+                    let mut response = limit_body(response, self.options.max_response_size, &url)?;
+                    *response.status_mut() = StatusCode::OK;
+                    *response.body_mut() = cached.body;
+                    return Ok(Response::from(response));
+                }
+            }
+            if !response.status().is_redirection() || response.status() == StatusCode::NOT_MODIFIED {
+                let response = limit_body(response, self.options.max_response_size, &url)?;
+                let etag = response.headers().get("ETag").and_then(|value| value.to_str().ok());
+                if let (Some(key), Some(etag), true) = (etag_key, etag, response.status() == StatusCode::OK) {
+                    let cached = EtagResponse { etag: etag.to_string(), body: response.body().clone() };
+                    self.etags.lock().unwrap().insert(key, cached);
+                }
+                return Ok(Response::from(response));
+            }
+            let location = response.headers()
+                .get("Location")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+                .ok_or_else(|| io::Error::other(format!("Redirect from {} without Location header", url)))?;
+
+            if response.status() == StatusCode::MOVED_PERMANENTLY {
+                if let Some(old_name) = repo_full_name_from_url(&url) {
+                    let new_name = self.resolve_renamed_repo(&location)?;
+                    if new_name != old_name {
+                        info!("Repository {} was renamed to {}", old_name, new_name);
+                        self.renames.lock().unwrap().insert(old_name, new_name);
+                    }
+                }
+            }
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(io::Error::other(format!("Too many redirects for {}", url)));
+            }
+            debug!("Following redirect from {} to {}", url, location);
+            url = location;
+        }
+    }
+
+    /// Lists the repositories of an organization. GitHub answers 404 for
+    /// organizations the token can't see, so that becomes a permission error.
+    fn fetch_org_repositories(&self, org: &str) -> io::Result<Vec<GitHubRepository>> {
+        let api_url = format!("{}/orgs/{}/repos", self.api_url, org);
+        info!("Listing the repositories of organization {}", org);
+        debug!("Fetching repositories from URL: {}", api_url);
+
+        match self.fetch_all_pages(&api_url, |page| self.filter_repositories(page), self.options.max_repos) {
+            Ok(repos) => {
+                debug!("Fetched {} repositories", repos.len());
+                Ok(repos)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound || err.kind() == ErrorKind::PermissionDenied => {
+                Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("Organization {} does not exist or the token has no access to it: {}", org, err),
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Web host matching the API, which serves git and LFS endpoints: the
+    /// Enterprise Server API lives under `/api/v3` of its web host.
+    fn web_url(&self) -> String {
+        if self.api_url == GITHUB_API_URL {
+            return GITHUB_WEB_URL.to_string();
+        }
+        self.api_url.strip_suffix("/api/v3").unwrap_or(&self.api_url).to_string()
+    }
+
+    /// Checks the token with `GET /user` before anything else is fetched, so
+    /// a bad token fails the mount with a clear message, and returns the
+    /// login it belongs to. `None` when running unauthenticated.
+    pub(crate) fn verify_token(&self) -> io::Result<Option<String>> {
+        if !self.is_authenticated() {
+            return Ok(None);
+        }
+        match self.authenticated_login() {
+            Ok(login) => {
+                info!("Authenticated to GitHub as {}", login);
+                Ok(Some(login))
+            }
+            Err(err) if api_status(&err) == Some(StatusCode::UNAUTHORIZED) => Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                "Invalid or expired GitHub token (HTTP 401); check --token or GITHUB_TOKEN",
+            )),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns true when the mounted owner is the user the token belongs
+    /// to. Logins are case-insensitive on GitHub.
+    fn is_authenticated_owner(&self) -> io::Result<bool> {
+        let login = match &self.login {
+            Some(login) => login.clone(),
+            // Without `new` (e.g. bench) the login wasn't looked up yet
+            None => self.authenticated_login()?,
+        };
+        Ok(login.eq_ignore_ascii_case(&self.username))
+    }
+
+    /// Login of the user the token belongs to.
+    fn authenticated_login(&self) -> io::Result<String> {
+        let user = self.get_json::<AuthenticatedUser>(&format!("{}/user", self.api_url))?;
+        Ok(user.login)
+    }
+
+    /// Finds the new `full_name` of a repository from a redirect target,
+    /// which is either `/repos/{owner}/{name}/...` or `/repositories/{id}/...`.
+    fn resolve_renamed_repo(&self, location: &str) -> io::Result<String> {
+        if let Some(full_name) = repo_full_name_from_url(location) {
+            return Ok(full_name);
+        }
+        let id = location
+            .find("/repositories/")
+            .map(|start| &location[start + "/repositories/".len()..])
+            .and_then(|rest| rest.split(['/', '?']).next())
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| io::Error::other(format!("Unexpected redirect location: {}", location)))?;
+
+        let repo = self.get_json::<RepositoryName>(&format!("{}/repositories/{}", self.api_url, id))?;
+        Ok(repo.full_name)
+    }
+
+    /// Returns the current name of a repository, accounting for renames
+    /// detected while the filesystem is mounted.
+    fn current_full_name(&self, full_name: &str) -> String {
+        self.renames.lock().unwrap()
+            .get(full_name)
+            .cloned()
+            .unwrap_or_else(|| full_name.to_string())
+    }
+
+    /// Updates the stored `full_name` of a repository if it was renamed and
+    /// returns the name to use for requests.
+    fn sync_repo_name(&self, repo_id: u64) -> io::Result<String> {
+        let stored = self.repos.read().unwrap()
+            .get(&repo_id)
+            .map(|repo| repo.full_name.clone())
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "Repository not found"))?;
+        let full_name = self.current_full_name(&stored);
+        if full_name != stored {
+            if let Some(repo) = self.repos.write().unwrap().get_mut(&repo_id) {
+                repo.full_name = full_name.clone();
+            }
+        }
+        Ok(full_name)
+    }
+
+    /// Applies the `--include`/`--exclude`, repository flag and license
+    /// filters, in that order.
+    fn filter_repositories(&self, repos: Vec<GitHubRepository>) -> Vec<GitHubRepository> {
+        let repos = filter_by_patterns(repos, &self.options.include, &self.options.exclude);
+        let repos = filter_by_flags(repos, &self.options);
+        filter_by_license(repos, &self.options.licenses)
+    }
+
+    /// Lists the repositories to mount, already filtered and capped at
+    /// `max_repos`.
+    pub fn fetch_repositories(&self) -> Result<Vec<GitHubRepository>, io::Error> {
+        if let Some(org) = &self.options.org {
+            return self.fetch_org_repositories(org);
+        }
+        let api_url = if !self.is_authenticated() {
+            info!("No token given, listing the public repositories of {}", self.username);
+            format!("{}/users/{}/repos", self.api_url, self.username)
+        } else if self.is_authenticated_owner()? {
+            info!("Listing the repositories of the authenticated user {}, including private ones", self.username);
+            format!("{}/user/repos", self.api_url)
+        } else {
+            info!("{} is not the authenticated user, listing only the repositories it shows to others", self.username);
+            format!("{}/users/{}/repos", self.api_url, self.username)
+        };
+        debug!("Fetching repositories from URL: {}", api_url);
+
+        let repos = self.fetch_all_pages(&api_url, |page| self.filter_repositories(page), self.options.max_repos)?;
+        debug!("Fetched {} repositories", repos.len());
+        Ok(repos)
+    }
+
+    /// Fetches every page of a list endpoint. Follows the `Link` header when
+    /// present; when a proxy strips it, keeps requesting the next `page`
+    /// number for as long as pages come back full.
+    ///
+    /// Each page goes through `filter`; pagination stops early once `limit`
+    /// items were kept.
+    fn fetch_all_pages<T: DeserializeOwned>(
+        &self,
+        api_url: &str,
+        filter: impl Fn(Vec<T>) -> Vec<T>,
+        limit: Option<usize>,
+    ) -> io::Result<Vec<T>> {
+        let separator = if api_url.contains('?') { '&' } else { '?' };
+        let mut url = format!("{}{}per_page={}", api_url, separator, PER_PAGE);
+        let mut items = Vec::new();
+        let mut listed = 0;
+        for page in 1..=MAX_PAGES {
+            let response = self.send_request(&url)?;
+            if !response.status().is_success() {
+                return Err(api_error(response));
+            }
+            let link = response.headers()
+                .get("Link")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string());
+            let batch = parse_json::<Vec<T>>(response)?;
+            // An empty page ends the pagination even if the Link points further
+            if batch.is_empty() {
+                return Ok(items);
+            }
+            let full_page = batch.len() >= PER_PAGE;
+            listed += batch.len();
+            items.extend(filter(batch));
+            if let Some(limit) = limit.filter(|limit| items.len() >= *limit) {
+                let next_page = match &link {
+                    Some(link) => next_page_url(link).is_some(),
+                    None => full_page,
+                };
+                let more = if items.len() > limit || next_page { ", more available" } else { "" };
+                items.truncate(limit);
+                info!("Loaded {} of {} listed items from {} (limit {}{})", items.len(), listed, api_url, limit, more);
+                return Ok(items);
+            }
+
+            url = match link {
+                Some(link) => match next_page_url(&link) {
+                    Some(next) => next,
+                    None => return Ok(items),
+                },
+                // Without a Link (proxies, Enterprise): a full page means there is more
+                None if full_page => {
+                    debug!("No Link header on page {} of {}, requesting page {}", page, api_url, page + 1);
+                    format!("{}{}per_page={}&page={}", api_url, separator, PER_PAGE, page + 1)
                 }
+                None => return Ok(items),
+            };
+        }
+        warn!("Stopped paginating {} after {} pages", api_url, MAX_PAGES);
+        Ok(items)
+    }
+
+    /// Fetches the metadata of a single repository.
+    pub(crate) fn fetch_repository(&self, full_name: &str) -> io::Result<GitHubRepository> {
+        let api_url = format!("{}/repos/{}", self.api_url, self.current_full_name(full_name));
+        debug!("Fetching repository from URL: {}", api_url);
+
+        self.get_json::<GitHubRepository>(&api_url)
+    }
+
+    /// Checks every repository for a changed default branch and reloads the
+    /// ones that moved, so the new branch's content is served. Runs at most
+    /// once per `DEFAULT_BRANCH_CHECK_INTERVAL`.
+    fn refresh_default_branches(&self) {
+        {
+            let mut last_check = self.last_branch_check.lock().unwrap();
+            if last_check.is_some_and(|at| at.elapsed() < DEFAULT_BRANCH_CHECK_INTERVAL) {
                 return;
             }
+            *last_check = Some(Instant::now());
         }
 
-        reply.error(ENOENT);
+        let repos: Vec<(u64, GitHubRepository)> = self.repos.read().unwrap()
+            .iter()
+            .map(|(&inode, repo)| (inode, repo.clone()))
+            .collect();
+        for (repo_id, repo) in repos {
+            let latest = match self.fetch_repository(&repo.full_name) {
+                Ok(latest) => latest,
+                Err(err) => {
+                    error!("Failed to check default branch of {}: {}", repo.full_name, err);
+                    continue;
+                }
+            };
+            if latest.default_branch == repo.default_branch {
+                continue;
+            }
+            info!(
+                "Default branch of {} changed from {:?} to {:?}, reloading",
+                repo.full_name, repo.default_branch, latest.default_branch
+            );
+            if let Some(cached) = self.repos.write().unwrap().get_mut(&repo_id) {
+                cached.default_branch = latest.default_branch;
+            }
+            self.unpin_refs(Some(&self.current_full_name(&repo.full_name)));
+            self.forget_listings(repo_id);
+            self.expanded.write().unwrap().remove(&repo_id);
+            if let Err(err) = self.load_files(repo_id, "") {
+                error!("Failed to reload {}: {}", repo.full_name, err);
+            }
+        }
+    }
+
+    /// Runs `fetch` unless the resource `key` failed recently. After each
+    /// failure the next attempt waits twice as long, up to
+    /// `MAX_REFETCH_INTERVAL`; a success clears the backoff.
+    fn with_refetch_backoff<T>(&self, key: String, fetch: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        let interval = match self.options.refetch_interval_on_error {
+            Some(interval) => interval,
+            None => return fetch(),
+        };
+        if let Some(backoff) = self.backoff.lock().unwrap().get(&key) {
+            let now = Instant::now();
+            if now < backoff.next_attempt {
+                debug!("Skipping refetch of {} for another {:?}", key, backoff.next_attempt - now);
+                return Err(io::Error::new(
+                    ErrorKind::WouldBlock,
+                    format!("{} failed recently, retrying in {:?}", key, backoff.next_attempt - now),
+                ));
+            }
+        }
+
+        let result = fetch();
+        let mut backoffs = self.backoff.lock().unwrap();
+        if result.is_ok() {
+            backoffs.remove(&key);
+        } else {
+            let failures = backoffs.get(&key).map_or(1, |backoff| backoff.failures + 1);
+            let wait = interval.saturating_mul(1 << (failures - 1).min(16)).min(MAX_REFETCH_INTERVAL);
+            debug!("{} failed {} times, next attempt in {:?}", key, failures, wait);
+            backoffs.insert(key, RefetchBackoff { failures, next_attempt: Instant::now() + wait });
+        }
+        result
+    }
+
+    /// Contents API URL of a path, which is normalized so that stray
+    /// slashes never produce a malformed URL.
+    fn contents_url(&self, repo_full_name: &str, path: &str) -> String {
+        format!("{}/repos/{}/contents/{}", self.api_url, self.current_full_name(repo_full_name), normalize_path(path))
+    }
+
+    /// Returns true when a path falls under one of the `--live-prefix`
+    /// options, which are relative to the mount root.
+    fn is_live(&self, repo_full_name: &str, path: &str) -> bool {
+        if self.options.live_prefixes.is_empty() {
+            return false;
+        }
+        let name = self.repos.read().unwrap()
+            .values()
+            .find(|repo| repo.full_name == repo_full_name)
+            .map(|repo| repo.name.clone());
+        let mount_path = match name {
+            Some(name) => join_path(&name, path),
+            None => return false,
+        };
+        self.options.live_prefixes.iter().any(|prefix| {
+            let prefix = normalize_path(prefix);
+            mount_path == prefix || mount_path.starts_with(&format!("{}/", prefix))
+        })
+    }
+
+    /// Key of a file in the content cache: its blob SHA when known, so that
+    /// identical files in different repositories are downloaded once.
+    fn content_key(&self, repo_full_name: &str, path: &str) -> String {
+        let path_key = format!("{}/{}", self.current_full_name(repo_full_name), normalize_path(path));
+        self.shas.read().unwrap().get(&path_key).cloned().unwrap_or(path_key)
+    }
+
+    pub(crate) fn fetch_file_content(&self, repo_full_name: &str, path: &str) -> Result<Vec<u8>, io::Error> {
+        if self.is_live(repo_full_name, path) {
+            debug!("{}/{} is under a live prefix, bypassing the content cache", repo_full_name, path);
+            return self.download_file_content(repo_full_name, path);
+        }
+        let cache_key = self.content_key(repo_full_name, path);
+        let cached = self.contents.lock().unwrap().get(&cache_key);
+        if let Some(content) = cached {
+            let hits = self.metrics.cache_hits.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!("Serving {} from the content cache ({} hits)", cache_key, hits);
+            return Ok(content);
+        }
+        let misses = self.metrics.cache_misses.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!("{} is not in the content cache ({} misses)", cache_key, misses);
+        let backoff_key = format!("{}/{}", self.current_full_name(repo_full_name), normalize_path(path));
+        let content = self.with_refetch_backoff(backoff_key, || self.download_file_content(repo_full_name, path))?;
+        self.contents.lock().unwrap().insert(cache_key, content.clone());
+        Ok(content)
+    }
+
+    fn download_file_content(&self, repo_full_name: &str, path: &str) -> Result<Vec<u8>, io::Error> {
+        let content = self.download_git_content(repo_full_name, path)?;
+        if !self.options.resolve_lfs {
+            return Ok(content);
+        }
+        match LfsPointer::parse(&content) {
+            Some(pointer) => {
+                debug!("{}/{} is an LFS pointer to {}", repo_full_name, path, pointer.oid);
+                self.fetch_lfs_object(repo_full_name, &pointer)
+            }
+            None => Ok(content),
+        }
+    }
+
+    /// Downloads an LFS object through the repository's batch API.
+    fn fetch_lfs_object(&self, repo_full_name: &str, pointer: &LfsPointer) -> io::Result<Vec<u8>> {
+        let batch_url = lfs::batch_url(&self.web_url(), &self.current_full_name(repo_full_name));
+        debug!("Requesting LFS object {} from {}", pointer.oid, batch_url);
+        let mut request = self.client.post(&batch_url)
+            .header("Accept", lfs::LFS_MEDIA_TYPE)
+            .header("Content-Type", lfs::LFS_MEDIA_TYPE)
+            .header("User-Agent", "GitHubFS");
+        if let Some(token) = self.token.read().unwrap().as_ref() {
+            request = request.basic_auth(&self.username, Some(token));
+        }
+        let request = request.json(&lfs::BatchRequest::download(pointer));
+        let response = self.send_traced("POST", &batch_url, request)
+            .map_err(|err| io::Error::other(format!("Failed to send LFS batch request: {}", err)))?;
+        if !response.status().is_success() {
+            return Err(api_error(response));
+        }
+        let batch = response.json::<lfs::BatchResponse>()
+            .map_err(|err| io::Error::other(format!("Failed to parse LFS batch response: {}", err)))?;
+        let object = batch.objects.into_iter()
+            .find(|object| object.oid == pointer.oid)
+            .ok_or_else(|| io::Error::other(format!("LFS batch response has no object {}", pointer.oid)))?;
+        if let Some(err) = object.error {
+            let message = format!("LFS object {} unavailable ({}): {}", pointer.oid, err.code, err.message);
+            return Err(match err.code {
+                404 => io::Error::new(ErrorKind::NotFound, message),
+                _ => io::Error::other(message),
+            });
+        }
+        let action = object.actions
+            .and_then(|actions| actions.download)
+            .ok_or_else(|| io::Error::other(format!("No download action for LFS object {}", pointer.oid)))?;
+
+        // The href usually redirects to storage; it doesn't get the token
+        let mut url = action.href;
+        let mut headers = action.header;
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = self.client.get(&url).header("User-Agent", "GitHubFS");
+            for (name, value) in &headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            // Signed storage URLs carry credentials in the query
+            let traced_url = url.split('?').next().unwrap_or_default();
+            let response = self.send_traced("GET", traced_url, request)
+                .map_err(|err| io::Error::other(format!("Failed to download LFS object {}: {}", pointer.oid, err)))?;
+            if response.status().is_redirection() {
+                url = response.headers()
+                    .get("Location")
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string())
+                    .ok_or_else(|| io::Error::other(format!("Redirect from {} without Location header", url)))?;
+                headers.clear();
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(api_error(response));
+            }
+            let content = limit_body(response, self.options.max_response_size, &url)?.into_body();
+            if content.len() as u64 != pointer.size {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("LFS object {} has {} bytes, expected {}", pointer.oid, content.len(), pointer.size),
+                ));
+            }
+            return Ok(content);
+        }
+        Err(io::Error::other(format!("Too many redirects for {}", url)))
+    }
+
+    /// Downloads a file through the contents API, as stored in git.
+    fn download_git_content(&self, repo_full_name: &str, path: &str) -> Result<Vec<u8>, io::Error> {
+        let branch = self.origin_branch(repo_full_name, path).or_else(|| self.options.reference.clone());
+        let pinned = if self.is_live(repo_full_name, path) { None } else { self.pinned_ref(repo_full_name, branch.as_deref()) };
+        let git_ref = pinned.or(branch);
+        let api_url = with_ref(self.contents_url(repo_full_name, path), git_ref.as_deref())?;
+        debug!("Fetching file content from URL: {}", api_url);
+
+        let body = self.get_bytes(&api_url, GITHUB_JSON)?;
+        let content = parse_file_content(&body, repo_full_name, path)?;
+        if content.content_type == "submodule" {
+            // Submodules have no content; by default they are empty files
+            if self.options.include_submodule_gitlink_content {
+                return Ok(content.submodule_text().into_bytes());
+            }
+            return Ok(Vec::new());
+        }
+        if let (true, Some(target)) = (content.content_type == "symlink", &content.target) {
+            return Ok(target.clone().into_bytes());
+        }
+        match content.encoding.as_str() {
+            "base64" => decode_base64(&content.content)
+                .map_err(|err| {
+                    error!("Failed to decode base64 content: {}", err);
+                    io::Error::other(format!("Failed to decode base64 content: {}", err))
+                }),
+            // Files over 1 MB come without content; fetch the blob by SHA
+            "" | "none" if content.content.is_empty() && !content.sha.is_empty() => {
+                debug!("No inline content for {}, falling back to the blobs API", api_url);
+                self.fetch_blob(repo_full_name, &content.sha)
+            }
+            "" | "none" if content.content.is_empty() => {
+                debug!("No inline content for {}, falling back to raw download", api_url);
+                self.fetch_raw_content(&api_url)
+            }
+            encoding => {
+                error!("Unknown content encoding: {}", encoding);
+                Err(io::Error::other(format!("Unknown content encoding: {}", encoding)))
+            }
+        }
+    }
+
+    /// Fetches a blob by SHA through the git blobs API, which serves files
+    /// up to 100 MB.
+    fn fetch_blob(&self, repo_full_name: &str, sha: &str) -> io::Result<Vec<u8>> {
+        let api_url = format!("{}/repos/{}/git/blobs/{}", self.api_url, self.current_full_name(repo_full_name), sha);
+        debug!("Fetching blob from URL: {}", api_url);
+
+        let blob = self.get_json::<GitBlob>(&api_url)?;
+        match blob.encoding.as_str() {
+            "base64" => decode_base64(&blob.content)
+                .map_err(|err| io::Error::other(format!("Failed to decode base64 blob {}: {}", sha, err))),
+            "utf-8" => Ok(blob.content.into_bytes()),
+            encoding => Err(io::Error::other(format!("Unknown blob encoding: {}", encoding))),
+        }
+    }
+
+    /// Fetches the raw bytes of a file through the contents API raw media type.
+    fn fetch_raw_content(&self, api_url: &str) -> Result<Vec<u8>, io::Error> {
+        self.get_bytes(api_url, "application/vnd.github.raw")
+    }
+
+    /// Downloads the files of a freshly loaded directory that are no larger
+    /// than `prefetch_content_max`, using a small pool of worker threads.
+    fn prefetch_contents(&self, full_name: &str, files: &[GitHubFile]) {
+        let small: Vec<&GitHubFile> = files.iter()
+            .filter(|file| file.file_type == "file" && file.size <= self.options.prefetch_content_max)
+            .collect();
+        if small.is_empty() {
+            return;
+        }
+        debug!("Prefetching {} small files from {}", small.len(), full_name);
+
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..PREFETCH_WORKERS.min(small.len()) {
+                scope.spawn(|| {
+                    while let Some(file) = small.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if let Err(err) = self.fetch_file_content(full_name, &file.path) {
+                            debug!("Failed to prefetch {}: {}", file.path, err);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Allocates a new inode. Inode numbers are never handed out twice in a
+    /// mount: a path keeps its inode for the life of the process (see
+    /// `path_inode`), and when the object behind a path is replaced by one
+    /// of another kind its generation is bumped instead (see
+    /// `store_listing`), so `(inode, generation)` always names one object.
+    fn next_inode(&self) -> u64 {
+        self.next_inode.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Generation of an inode, passed to the kernel with every entry.
+    fn generation(&self, ino: u64) -> u64 {
+        self.generations.read().unwrap().get(&ino).map_or(0, |(_kind, generation)| *generation)
+    }
+
+    /// Fetches the listing of one directory, without touching the inode
+    /// tables.
+    pub(crate) fn fetch_directory(&self, repo_full_name: &str, path: &str) -> io::Result<Vec<GitHubFile>> {
+        let files = if let Some(reference) = &self.options.reference {
+            // Otherwise an invalid ref would look like a 404 for the path
+            if self.pinned_ref(repo_full_name, Some(reference)).is_none() {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Ref {} could not be resolved in {}", reference, repo_full_name),
+                ));
+            }
+            self.fetch_directory_at(repo_full_name, path, Some(reference))?
+        } else if self.options.union_branches.is_empty() {
+            self.fetch_directory_at(repo_full_name, path, None)?
+        } else {
+            self.fetch_union_directory(repo_full_name, path)?
+        };
+        let full_name = self.current_full_name(repo_full_name);
+        let shas = files.iter()
+            .filter(|file| file.file_type == "file" && !file.sha.is_empty())
+            .map(|file| (format!("{}/{}", full_name, file.path), file.sha.clone()));
+        self.shas.write().unwrap().extend(shas);
+        Ok(files)
+    }
+
+    /// Root listing of a repository in `readme_only` mode: just its README,
+    /// or nothing when it has none. The README may live in a subdirectory
+    /// (`docs/`), but is shown at the root under its own name.
+    fn fetch_readme(&self, repo_full_name: &str) -> io::Result<Vec<GitHubFile>> {
+        let api_url = format!("{}/repos/{}/readme", self.api_url, self.current_full_name(repo_full_name));
+        let api_url = with_ref(api_url, self.options.reference.as_deref())?;
+        debug!("Fetching README from URL: {}", api_url);
+
+        let readme = match self.get_json::<GitHubFile>(&api_url) {
+            Ok(readme) => readme,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("{} has no README", repo_full_name);
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err),
+        };
+        if !readme.sha.is_empty() {
+            let key = format!("{}/{}", self.current_full_name(repo_full_name), readme.path);
+            self.shas.write().unwrap().insert(key, readme.sha.clone());
+        }
+        Ok(vec![readme])
+    }
+
+    /// Merges the listing of one directory across `union_branches`. An
+    /// entry present in several branches, whatever its type, comes from the
+    /// first branch listing it; the others only add the names it lacks.
+    fn fetch_union_directory(&self, repo_full_name: &str, path: &str) -> io::Result<Vec<GitHubFile>> {
+        let mut merged: Vec<GitHubFile> = Vec::new();
+        let mut origins = Vec::new();
+        let mut missing = None;
+        let mut found = false;
+        for branch in &self.options.union_branches {
+            let files = match self.fetch_directory_at(repo_full_name, path, Some(branch)) {
+                Ok(files) => files,
+                // The directory may only exist on some branches
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    debug!("{}/{} does not exist on branch {}", repo_full_name, path, branch);
+                    missing = Some(err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            for file in files {
+                if merged.iter().all(|existing| existing.name != file.name) {
+                    origins.push((format!("{}/{}", self.current_full_name(repo_full_name), file.path), branch.clone()));
+                    merged.push(file);
+                }
+            }
+            found = true;
+        }
+        if let (false, Some(err)) = (found, missing) {
+            return Err(err);
+        }
+        self.origins.write().unwrap().extend(origins);
+        Ok(merged)
+    }
+
+    /// Commit SHA that `git_ref` (the default branch when `None`) pointed
+    /// to the first time it was used, so a session sees one consistent
+    /// snapshot even if the branch moves. `None` when it can't be resolved,
+    /// e.g. for an empty repository.
+    fn pinned_ref(&self, repo_full_name: &str, git_ref: Option<&str>) -> Option<String> {
+        let full_name = self.current_full_name(repo_full_name);
+        let key = (full_name.clone(), git_ref.unwrap_or("HEAD").to_string());
+        if let Some(sha) = self.refs.lock().unwrap().get(&key) {
+            return Some(sha.clone());
+        }
+
+        let api_url = format!("{}/repos/{}/commits/{}", self.api_url, full_name, key.1);
+        debug!("Resolving ref from URL: {}", api_url);
+        let sha = match self.send_request_with_accept(&api_url, "application/vnd.github.sha") {
+            Ok(response) if response.status().is_success() => response.text().ok()?.trim().to_string(),
+            Ok(response) => {
+                debug!("Could not resolve {} of {}: HTTP {}", key.1, full_name, response.status());
+                return None;
+            }
+            Err(err) => {
+                debug!("Could not resolve {} of {}: {}", key.1, full_name, err);
+                return None;
+            }
+        };
+        debug!("Pinned {} of {} to {}", key.1, full_name, sha);
+        Some(self.refs.lock().unwrap().entry(key).or_insert(sha).clone())
+    }
+
+    /// Forgets the pinned refs of one repository, or of all when `None`.
+    fn unpin_refs(&self, repo_full_name: Option<&str>) {
+        let mut refs = self.refs.lock().unwrap();
+        match repo_full_name {
+            Some(full_name) => refs.retain(|(name, _ref), _sha| name != full_name),
+            None => refs.clear(),
+        }
+    }
+
+    /// Branch a path was taken from in union mode.
+    fn origin_branch(&self, repo_full_name: &str, path: &str) -> Option<String> {
+        let key = format!("{}/{}", self.current_full_name(repo_full_name), normalize_path(path));
+        self.origins.read().unwrap().get(&key).cloned()
+    }
+
+    /// Lists one directory at `git_ref`, or at the default branch.
+    fn fetch_directory_at(&self, repo_full_name: &str, path: &str, git_ref: Option<&str>) -> io::Result<Vec<GitHubFile>> {
+        let pinned = if self.is_live(repo_full_name, path) { None } else { self.pinned_ref(repo_full_name, git_ref) };
+        let git_ref = pinned.as_deref().or(git_ref);
+        let api_url = with_ref(self.contents_url(repo_full_name, path), git_ref)?;
+        debug!("Fetching files from URL: {}", api_url);
+
+        let response = self.send_request(&api_url)?;
+
+        if response.status().is_success() {
+            let files = parse_json::<Vec<GitHubFile>>(response)?;
+            debug!("Fetched {} files", files.len());
+            Ok(files)
+        } else if response.status() == StatusCode::FORBIDDEN {
+            let status = response.status();
+            let message = response.text().unwrap_or_default();
+            // The contents endpoint refuses directories that are too large
+            if message.to_lowercase().contains("too large") {
+                debug!("Directory {}/{} is too large for the contents API, falling back to git trees", repo_full_name, path);
+                return self.fetch_tree(repo_full_name, path, git_ref);
+            }
+            Err(status_error(status, &message))
+        } else {
+            Err(api_error(response))
+        }
+    }
+
+    /// Lists one directory through the git trees API, which accepts larger
+    /// directories than the contents API.
+    fn fetch_tree(&self, repo_full_name: &str, path: &str, git_ref: Option<&str>) -> io::Result<Vec<GitHubFile>> {
+        let git_ref = git_ref.unwrap_or("HEAD");
+        let path = normalize_path(path);
+        let tree_ish = if path.is_empty() { git_ref.to_string() } else { format!("{}:{}", git_ref, path) };
+        let api_url = format!("{}/repos/{}/git/trees/{}", self.api_url, self.current_full_name(repo_full_name), tree_ish);
+        debug!("Fetching tree from URL: {}", api_url);
+
+        let tree = self.get_json::<GitTree>(&api_url)?;
+        if tree.truncated {
+            warn!("Tree listing of {}/{} was truncated by GitHub", repo_full_name, path);
+        }
+        let files: Vec<GitHubFile> = tree.tree.into_iter().map(|entry| entry.into_file(&path)).collect();
+        debug!("Fetched {} files from tree", files.len());
+        Ok(files)
+    }
+
+    /// Listing of a directory inode, fetched on its first access and cached
+    /// in `files` afterwards. `None` when the inode is not a directory.
+    fn directory_listing(&self, ino: u64) -> io::Result<Option<Vec<GitHubFile>>> {
+        if let Some(files) = self.files.read().unwrap().get(&ino).cloned() {
+            return Ok(Some(files));
+        }
+        if self.repos.read().unwrap().contains_key(&ino) {
+            // Blocked repositories aren't retried; failed ones are
+            if self.repo_marker(ino).is_some_and(|marker| marker.name != ERROR_MARKER_NAME) {
+                return Ok(None);
+            }
+            self.load_repo_root(ino)?;
+        } else {
+            let (repo_id, path) = match (self.file_entry(ino), self.resolve(ino)) {
+                (Some(entry), Some((repo_id, path))) if entry.file_type == "dir" => (repo_id, path),
+                _ => return Ok(None),
+            };
+            self.load_files(repo_id, &path)?;
+        }
+        Ok(self.files.read().unwrap().get(&ino).cloned())
+    }
+
+    /// Drops the cached listings of a repository, so that its directories
+    /// are fetched again on their next access.
+    fn forget_listings(&self, repo_id: u64) {
+        let inodes: Vec<u64> = self.paths.read().unwrap()
+            .iter()
+            .filter(|(_inode, (repo, _path))| *repo == repo_id)
+            .map(|(&inode, _location)| inode)
+            .collect();
+        let mut files = self.files.write().unwrap();
+        files.remove(&repo_id);
+        for inode in &inodes {
+            files.remove(inode);
+        }
+        drop(files);
+        let mut expanded = self.expanded.write().unwrap();
+        expanded.remove(&repo_id);
+        for inode in &inodes {
+            expanded.remove(inode);
+        }
+    }
+
+    pub fn load_files(&self, repo_id: u64, path: &str) -> io::Result<Vec<GitHubFile>> {
+        let full_name = self.sync_repo_name(repo_id)?;
+        let backoff_key = format!("{}/{}/", full_name, normalize_path(path));
+        let result = self.with_refetch_backoff(backoff_key, || {
+            if self.options.readme_only && path.is_empty() {
+                self.fetch_readme(&full_name)
+            } else {
+                self.fetch_directory(&full_name, path)
+            }
+        });
+        let full_name = self.sync_repo_name(repo_id)?;
+
+        let files = match result {
+            Ok(files) => files,
+            Err(err) => {
+                if is_unavailable_for_legal_reasons(&err) {
+                    self.mark_blocked(repo_id, &full_name, &err);
+                }
+                return Err(err);
+            }
+        };
+
+        if self.options.prefetch {
+            self.prefetch_contents(&full_name, &files);
+        }
+
+        // Subdirectories are only loaded when accessed
+        self.store_listing(repo_id, self.path_inode(repo_id, path), &files);
+        Ok(files)
+    }
+
+    /// Caches the listing of directory `ino`, allocating the inodes of its
+    /// entries up front so that `readdir` and `lookup` only read them.
+    fn store_listing(&self, repo_id: u64, ino: u64, files: &[GitHubFile]) {
+        let inodes: Vec<u64> = files.iter().map(|file| self.path_inode(repo_id, &file.path)).collect();
+        let mut generations = self.generations.write().unwrap();
+        for (file, inode) in files.iter().zip(inodes) {
+            let (kind, generation) = generations.entry(inode).or_insert((file.kind(), 0));
+            // The path now holds another kind of object: old handles must
+            // not reach the new one
+            if *kind != file.kind() {
+                *kind = file.kind();
+                *generation += 1;
+                debug!("{} changed kind, inode {} is now generation {}", file.path, inode, generation);
+            }
+        }
+        drop(generations);
+        self.files.write().unwrap().insert(ino, files.to_vec());
+        // A new listing may contain names that didn't exist before
+        self.negative.lock().unwrap().retain(|(parent, _name), _expiry| *parent != ino);
+    }
+
+    /// Returns true when `name` was not found in `parent` less than
+    /// `negative_ttl` ago.
+    fn is_known_missing(&self, parent: u64, name: &OsStr) -> bool {
+        let mut negative = self.negative.lock().unwrap();
+        let key = (parent, name.to_os_string());
+        match negative.get(&key) {
+            Some(expiry) if *expiry > Instant::now() => true,
+            Some(_expired) => {
+                negative.remove(&key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Remembers that `name` doesn't exist in `parent`, if enabled.
+    fn remember_missing(&self, parent: u64, name: &OsStr) {
+        if let Some(ttl) = self.options.negative_ttl {
+            let mut negative = self.negative.lock().unwrap();
+            let now = Instant::now();
+            // Drop the expired ones so the map doesn't grow without bound
+            negative.retain(|_key, expiry| *expiry > now);
+            negative.insert((parent, name.to_os_string()), now + ttl);
+        }
+    }
+
+    /// Fetches the listing of a directory under a live prefix again,
+    /// replacing the cached one. `None` for other directories.
+    fn refetch_live_listing(&self, ino: u64) -> Option<Vec<GitHubFile>> {
+        let (repo_id, path) = self.resolve(ino)?;
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        if !self.is_live(&full_name, &path) {
+            return None;
+        }
+        match self.fetch_directory(&full_name, &path) {
+            Ok(files) => {
+                self.store_listing(repo_id, ino, &files);
+                Some(files)
+            }
+            Err(err) => {
+                error!("Failed to refetch live directory {}/{}: {}", full_name, path, err);
+                None
+            }
+        }
+    }
+
+    /// Returns true when the listing of `ino` should be replaced by the
+    /// placeholder entry because it sits beyond `max_depth_display`.
+    fn is_collapsed(&self, ino: u64, files: &[GitHubFile]) -> bool {
+        let max_depth = match self.options.max_depth_display {
+            Some(depth) => depth,
+            None => return false,
+        };
+        if ino == 1 || self.expanded.read().unwrap().contains(&ino) {
+            return false;
+        }
+        // The directory depth is the number of '/' in its children's paths
+        match files.first() {
+            Some(file) => file.path.matches('/').count() >= max_depth,
+            None => false,
+        }
+    }
+
+    /// Finds the entry `name` of directory `parent`, whose listing is
+    /// `files`, and returns its inode. Finding a child of a collapsed
+    /// directory expands it.
+    fn find_child(&self, parent: u64, files: &[GitHubFile], name: &OsStr) -> Option<u64> {
+        let file = files.iter().find(|file| OsStr::new(&file.name) == name)?;
+        let repo_id = self.repo_of(parent)?;
+        if self.is_collapsed(parent, files) {
+            debug!("Expanding collapsed directory {}", parent);
+            self.expanded.write().unwrap().insert(parent);
+        }
+        Some(self.path_inode(repo_id, &file.path))
+    }
+
+    /// Content of the placeholder of a collapsed directory: the names it
+    /// hides, one per line.
+    fn collapsed_text(&self, dir: u64) -> Option<Vec<u8>> {
+        let files = self.files.read().unwrap().get(&dir).cloned()?;
+        let text: String = files.iter().map(|file| format!("{}\n", file.name)).collect();
+        Some(text.into_bytes())
+    }
+
+    /// Records a repository as unavailable for legal reasons so it is shown
+    /// with a `.dmca` marker instead of its content.
+    fn mark_blocked(&self, repo_id: u64, full_name: &str, err: &io::Error) {
+        if self.markers.read().unwrap().get(&repo_id).is_some_and(|marker| marker.name == DMCA_MARKER_NAME) {
+            return;
+        }
+        info!("Repository {} is unavailable for legal reasons, skipping it", full_name);
+        let message = format!("{} is unavailable for legal reasons.\n{}\n", full_name, err);
+        self.set_marker(repo_id, DMCA_MARKER_NAME, message);
+    }
+
+    fn set_marker(&self, repo_id: u64, name: &'static str, message: String) {
+        let inode = synthetic_inode_for(repo_id, name);
+        self.markers.write().unwrap().insert(repo_id, RepoMarker { inode, name, message });
+    }
+
+    /// Returns the marker shown inside a repository directory, if any.
+    fn repo_marker(&self, repo_id: u64) -> Option<RepoMarker> {
+        self.markers.read().unwrap().get(&repo_id).cloned()
+    }
+
+    /// Returns the text of a marker inode.
+    fn marker_message(&self, ino: u64) -> Option<String> {
+        self.markers.read().unwrap()
+            .values()
+            .find(|marker| marker.inode == ino)
+            .map(|marker| marker.message.clone())
+    }
+
+    /// Returns true while a repository is quarantined. Quarantines end after
+    /// `QUARANTINE_COOLDOWN` or when a SIGHUP asked for a reload.
+    fn is_quarantined(&self, repo_id: u64) -> bool {
+        let mut failures = self.failures.lock().unwrap();
+        let quarantined_at = match failures.get(&repo_id).and_then(|failure| failure.quarantined_at) {
+            Some(at) => at,
+            None => return false,
+        };
+        if quarantined_at.elapsed() < QUARANTINE_COOLDOWN {
+            return true;
+        }
+        debug!("Quarantine of repository {} expired", repo_id);
+        failures.remove(&repo_id);
+        drop(failures);
+        self.markers.write().unwrap().remove(&repo_id);
+        false
+    }
+
+    /// Loads the root of a repository, counting consecutive failures and
+    /// quarantining the repository once `quarantine_after` is reached.
+    fn load_repo_root(&self, repo_id: u64) -> io::Result<()> {
+        if self.is_quarantined(repo_id) {
+            return Err(io::Error::other(format!("Repository {} is quarantined", repo_id)));
+        }
+        let err = match self.load_files(repo_id, "") {
+            Ok(_) => {
+                self.failures.lock().unwrap().remove(&repo_id);
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        let threshold = match self.options.quarantine_after {
+            // Attempts put off by the backoff don't count as failures
+            Some(threshold) if err.kind() != ErrorKind::WouldBlock => threshold,
+            _ => return Err(err),
+        };
+        let mut failures = self.failures.lock().unwrap();
+        let failure = failures.entry(repo_id).or_default();
+        failure.count += 1;
+        if failure.count >= threshold && failure.quarantined_at.is_none() {
+            failure.quarantined_at = Some(Instant::now());
+            drop(failures);
+            let full_name = self.repos.read().unwrap().get(&repo_id).map(|repo| repo.full_name.clone()).unwrap_or_default();
+            error!("Quarantining {} after {} consecutive failures", full_name, threshold);
+            if self.repo_marker(repo_id).is_none() {
+                let message = format!("{} failed to load {} times in a row and is quarantined.\nLast error: {}\n", full_name, threshold, err);
+                self.set_marker(repo_id, ERROR_MARKER_NAME, message);
+            }
+        }
+        Err(err)
+    }
+
+    /// Returns the owning repository and repo-relative path of an inode.
+    /// Repository roots resolve to an empty path.
+    fn resolve(&self, ino: u64) -> Option<(u64, String)> {
+        if self.repos.read().unwrap().contains_key(&ino) {
+            return Some((ino, String::new()));
+        }
+        self.paths.read().unwrap().get(&ino).cloned()
+    }
+
+    /// Returns the repository `full_name` and repo-relative path of an inode.
+    fn locate(&self, ino: u64) -> Option<(String, String)> {
+        let (repo_id, path) = self.resolve(ino)?;
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        Some((full_name, path))
+    }
+
+    /// Returns the inode of a path in a repository, allocating it (and its
+    /// reverse mapping) on first use. The repository root is the repository
+    /// inode itself.
+    fn path_inode(&self, repo_id: u64, path: &str) -> u64 {
+        let path = normalize_path(path);
+        if path.is_empty() {
+            return repo_id;
+        }
+        let key = (repo_id, path);
+        if let Some(&inode) = self.inodes.read().unwrap().get(&key) {
+            return inode;
+        }
+        let path = key.1.clone();
+        let inode = *self.inodes.write().unwrap().entry(key).or_insert_with(|| self.next_inode());
+        self.paths.write().unwrap().insert(inode, (repo_id, path));
+        inode
+    }
+
+    /// Returns the repository a directory inode belongs to.
+    fn repo_of(&self, dir: u64) -> Option<u64> {
+        self.resolve(dir).map(|(repo_id, _path)| repo_id)
+    }
+
+    /// Loads and caches the CODEOWNERS file of a repository, looking in the
+    /// same places GitHub does.
+    fn repo_codeowners(&self, repo_id: u64) -> Option<CodeOwners> {
+        if let Some(cached) = self.codeowners.read().unwrap().get(&repo_id) {
+            return cached.clone();
+        }
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        let parsed = CODEOWNERS_PATHS.iter().find_map(|path| {
+            match self.fetch_file_content(&full_name, path) {
+                Ok(content) => Some(CodeOwners::parse(&String::from_utf8_lossy(&content))),
+                Err(err) => {
+                    debug!("No CODEOWNERS at {} in {}: {}", path, full_name, err);
+                    None
+                }
+            }
+        });
+        self.codeowners.write().unwrap().insert(repo_id, parsed.clone());
+        parsed
+    }
+
+    /// Loads and caches the root `.gitattributes` of a repository.
+    fn repo_gitattributes(&self, repo_id: u64) -> Option<GitAttributes> {
+        if let Some(cached) = self.gitattributes.read().unwrap().get(&repo_id) {
+            return cached.clone();
+        }
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        let parsed = match self.fetch_file_content(&full_name, GITATTRIBUTES_PATH) {
+            Ok(content) => Some(GitAttributes::parse(&String::from_utf8_lossy(&content))),
+            Err(err) => {
+                debug!("No .gitattributes in {}: {}", full_name, err);
+                None
+            }
+        };
+        self.gitattributes.write().unwrap().insert(repo_id, parsed.clone());
+        parsed
+    }
+
+    /// Text/binary classification of a file from `.gitattributes`. Only
+    /// consulted by the features that care about it (line ending conversion
+    /// and `--hide-binary`).
+    fn attribute_binary(&self, repo_id: u64, path: &str) -> Option<bool> {
+        let needed = self.options.line_endings != LineEndings::Preserve
+            || self.options.hide_binary
+            || self.options.hide_binary_strict;
+        if !needed {
+            return None;
+        }
+        self.repo_gitattributes(repo_id)?.is_binary(path)
+    }
+
+    /// Returns the inode of the `.listing.json` file of directory `dir`,
+    /// allocating it on first use.
+    fn listing_inode(&self, dir: u64) -> u64 {
+        self.synthetic_inode(dir, JSON_LISTING_NAME)
+    }
+
+    /// Maps a `.listing.json` inode back to the directory it describes.
+    fn listing_dir(&self, ino: u64) -> Option<u64> {
+        match self.synthetic_entry(ino)? {
+            (dir, JSON_LISTING_NAME) => Some(dir),
+            _ => None,
+        }
+    }
+
+    /// Serializes the entries of directory `dir` for `.listing.json`.
+    fn listing_json(&self, dir: u64) -> Option<Vec<u8>> {
+        let files = self.files.read().unwrap().get(&dir).cloned()?;
+        let entries: Vec<ListingEntry> = files.iter()
+            .map(|file| ListingEntry {
+                name: &file.name,
+                file_type: &file.file_type,
+                size: file.size,
+                sha: &file.sha,
+                mode: file.mode(),
+            })
+            .collect();
+        serde_json::to_vec_pretty(&entries).ok()
+    }
+
+    /// Serializes the state of the mount for `.githubfs-status`.
+    fn status_json(&self) -> Vec<u8> {
+        let (cached_files, cached_bytes) = {
+            let contents = self.contents.lock().unwrap();
+            (contents.len(), contents.size())
+        };
+        let remaining = self.rate_limit_remaining.load(Ordering::Relaxed);
+        let status = MountStatus {
+            owner: &self.username,
+            authenticated: self.is_authenticated(),
+            login: self.login.as_deref(),
+            api_url: &self.api_url,
+            repositories: self.repos.read().unwrap().len(),
+            requests: self.metrics.requests.load(Ordering::Relaxed),
+            cache_hits: self.metrics.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.metrics.cache_misses.load(Ordering::Relaxed),
+            not_modified: self.metrics.not_modified.load(Ordering::Relaxed),
+            cached_files,
+            cached_bytes,
+            rate_limit_remaining: (remaining != u64::MAX).then_some(remaining),
+        };
+        let mut json = serde_json::to_vec_pretty(&status).unwrap_or_default();
+        json.push(b'\n');
+        json
+    }
+
+    /// Description of a repository for `.description`, or `None` when the
+    /// option is off or the repository has no description.
+    fn description_text(&self, repo_id: u64) -> Option<Vec<u8>> {
+        if !self.options.with_descriptions {
+            return None;
+        }
+        let description = self.repos.read().unwrap().get(&repo_id)?.description.clone()?;
+        Some(format!("{}\n", description).into_bytes())
+    }
+
+    /// Lists the entries of directory `dir` with human-readable sizes for
+    /// `.sizes`. Directories show `-`.
+    fn sizes_text(&self, dir: u64) -> Option<Vec<u8>> {
+        let files = self.files.read().unwrap().get(&dir).cloned()?;
+        let text: String = files.iter()
+            .map(|file| {
+                let size = if file.file_type == "dir" { "-".to_string() } else { format_size(file.size) };
+                format!("{:>10}  {}\n", size, file.name)
+            })
+            .collect();
+        Some(text.into_bytes())
+    }
+
+    /// Returns the inode of a synthetic entry of `repo_id` (or of a directory,
+    /// for `.sizes`), allocating it on first use. When its preferred inode
+    /// is already taken, the next free one is used.
+    fn synthetic_inode(&self, repo_id: u64, name: &'static str) -> u64 {
+        if let Some(&inode) = self.synthetic.read().unwrap().get(&(repo_id, name)) {
+            return inode;
+        }
+        let mut inode = synthetic_inode_for(repo_id, name);
+        loop {
+            let real = self.repos.read().unwrap().contains_key(&inode) || self.paths.read().unwrap().contains_key(&inode);
+            if !real {
+                let mut synthetic = self.synthetic.write().unwrap();
+                if let Some(&existing) = synthetic.get(&(repo_id, name)) {
+                    return existing;
+                }
+                if synthetic.values().all(|&taken| taken != inode) {
+                    synthetic.insert((repo_id, name), inode);
+                    return inode;
+                }
+            }
+            inode = next_synthetic_inode(inode);
+        }
+    }
+
+    /// Maps a synthetic inode back to its repository and name.
+    fn synthetic_entry(&self, ino: u64) -> Option<(u64, &'static str)> {
+        self.synthetic.read().unwrap()
+            .iter()
+            .find(|(_key, &inode)| inode == ino)
+            .map(|(&key, _inode)| key)
+    }
+
+    fn meta_enabled(&self) -> bool {
+        self.options.with_ci || self.options.with_license
+    }
+
+    /// Files currently available in the `.meta` directory of a repository.
+    fn meta_files(&self, repo_id: u64) -> Vec<(&'static str, Vec<u8>)> {
+        let mut files = Vec::new();
+        if self.options.with_ci {
+            if let Some(status) = self.ci_status(repo_id) {
+                files.push((CI_STATUS_NAME, status.into_bytes()));
+            }
+        }
+        if self.options.with_license {
+            let spdx_id = self.repos.read().unwrap()
+                .get(&repo_id)
+                .and_then(|repo| repo.license.as_ref()?.spdx_id.clone());
+            if let Some(spdx_id) = spdx_id {
+                files.push((LICENSE_NAME, format!("{}\n", spdx_id).into_bytes()));
+            }
+        }
+        files
+    }
+
+    /// Content of a synthetic file inode (`.sizes` or a `.meta` file).
+    fn synthetic_content(&self, ino: u64) -> Option<Vec<u8>> {
+        let (owner, name) = self.synthetic_entry(ino)?;
+        match name {
+            META_DIR_NAME => None,
+            SIZES_NAME => self.sizes_text(owner),
+            DEEP_PLACEHOLDER_NAME => self.collapsed_text(owner),
+            STATUS_NAME => Some(self.status_json()),
+            DESCRIPTION_NAME => self.description_text(owner),
+            _ => self.meta_file_content(owner, name),
+        }
+    }
+
+    /// Content of the `.meta` file `name` of a repository.
+    fn meta_file_content(&self, repo_id: u64, name: &str) -> Option<Vec<u8>> {
+        self.meta_files(repo_id)
+            .into_iter()
+            .find(|(file_name, _content)| *file_name == name)
+            .map(|(_name, content)| content)
+    }
+
+    /// Latest workflow run conclusion of a repository, cached for
+    /// `META_TTL`. `None` when Actions is disabled or has never run.
+    fn ci_status(&self, repo_id: u64) -> Option<String> {
+        if let Some((fetched_at, status)) = self.ci_status.lock().unwrap().get(&repo_id) {
+            if fetched_at.elapsed() < META_TTL {
+                return status.clone();
+            }
+        }
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        let api_url = format!("{}/repos/{}/actions/runs?per_page=1", self.api_url, self.current_full_name(&full_name));
+        debug!("Fetching workflow runs from URL: {}", api_url);
+
+        let status = match self.send_request(&api_url) {
+            Ok(response) if response.status().is_success() => response.json::<WorkflowRuns>()
+                .map_err(|err| error!("Failed to parse JSON response: {}", err))
+                .ok()
+                .and_then(|runs| runs.status_line()),
+            Ok(response) => {
+                debug!("No workflow runs for {}: HTTP {}", full_name, response.status());
+                None
+            }
+            Err(err) => {
+                error!("Failed to fetch workflow runs for {}: {}", full_name, err);
+                None
+            }
+        };
+        self.ci_status.lock().unwrap().insert(repo_id, (Instant::now(), status.clone()));
+        status
+    }
+
+    /// Applies the configured content transformations to downloaded bytes.
+    /// `.gitattributes` decides whether the file is text when it can;
+    /// otherwise the NUL heuristic does.
+    fn transform_content(&self, location: Option<(u64, &str)>, content: Vec<u8>) -> Vec<u8> {
+        if self.options.line_endings == LineEndings::Preserve {
+            return content;
+        }
+        match location.and_then(|(repo_id, path)| self.attribute_binary(repo_id, path)) {
+            Some(true) => content,
+            Some(false) => transform::rewrite_line_endings(content, self.options.line_endings),
+            None => transform::convert_line_endings(content, self.options.line_endings),
+        }
+    }
+
+    /// Content served for a file inode, after transformations. It is kept
+    /// per inode so the chunked reads of one file fetch and transform it
+    /// once; files under a live prefix are fetched again on each read from
+    /// the start.
+    fn served_content(&self, ino: u64, offset: i64, repo_full_name: &str, path: &str) -> io::Result<Arc<[u8]>> {
+        let refetch = offset == 0 && self.is_live(repo_full_name, path);
+        if !refetch {
+            if let Some(content) = self.served.lock().unwrap().get(&ino) {
+                return Ok(content);
+            }
+        }
+        let content = self.fetch_file_content(repo_full_name, path)?;
+        let location = self.resolve(ino);
+        let content: Arc<[u8]> = self.transform_content(location.as_ref().map(|(repo_id, path)| (*repo_id, path.as_str())), content).into();
+        self.served.lock().unwrap().insert(ino, content.clone());
+        Ok(content)
+    }
+
+    /// Serves a read from the start of a file with range requests, growing
+    /// the downloaded prefix as reads advance. Returns `None` when the read
+    /// must go through the full download instead: the option is off, the
+    /// content is transformed or already cached, or the read skips ahead.
+    fn read_prefix(&self, ino: u64, file: &GitHubFile, offset: u64, size: u32) -> Option<Vec<u8>> {
+        if !self.options.open_in_pager || self.options.resolve_lfs || self.options.line_endings != LineEndings::Preserve {
+            return None;
+        }
+        let url = file.download_url.as_deref()?;
+        let (repo_id, path) = self.resolve(ino)?;
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        if self.is_live(&full_name, &path) {
+            return None;
+        }
+        let key = self.content_key(&full_name, &path);
+        if self.contents.lock().unwrap().contains_key(&key) {
+            return None;
+        }
+
+        let mut prefix = self.prefixes.read().unwrap().get(&key).cloned().unwrap_or_default();
+        let end = offset.saturating_add(u64::from(size)).min(file.size);
+        if offset > prefix.len() as u64 || offset >= end {
+            return None;
+        }
+        if end > prefix.len() as u64 {
+            let response = self.send_request_with_range(url, "application/octet-stream", Some((prefix.len() as u64, end))).ok()?;
+            if response.status() != StatusCode::PARTIAL_CONTENT {
+                // The server ignored the Range: the response is the whole file
+                if response.status().is_success() {
+                    let content = response.bytes().ok()?.to_vec();
+                    self.prefixes.write().unwrap().remove(&key);
+                    self.contents.lock().unwrap().insert(key, content);
+                }
+                return None;
+            }
+            prefix.extend_from_slice(&response.bytes().ok()?);
+            debug!("Downloaded {} of {} bytes of {}", prefix.len(), file.size, path);
+        }
+
+        let data = prefix[offset as usize..(end as usize).min(prefix.len())].to_vec();
+        if prefix.len() as u64 >= file.size {
+            self.prefixes.write().unwrap().remove(&key);
+            self.contents.lock().unwrap().insert(key, prefix);
+        } else {
+            self.prefixes.write().unwrap().insert(key, prefix);
+        }
+        Some(data)
+    }
+
+    /// Serves a read of a large file with a single range request for just
+    /// the bytes asked for. Returns `None` when the read must go through the
+    /// full download instead: the file is small, has no `download_url`, is
+    /// transformed or LFS-resolved, or is already cached. A server that
+    /// ignores `Range` answers with the whole file, which is cached and
+    /// sliced.
+    fn read_range(&self, ino: u64, file: &GitHubFile, offset: u64, size: u32) -> Option<Vec<u8>> {
+        if file.file_type != "file" || file.size < RANGE_READ_MIN_SIZE {
+            return None;
+        }
+        if self.options.resolve_lfs || self.options.line_endings != LineEndings::Preserve {
+            return None;
+        }
+        let url = file.download_url.as_deref()?;
+        let (repo_id, path) = self.resolve(ino)?;
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        let key = self.content_key(&full_name, &path);
+        if self.contents.lock().unwrap().contains_key(&key) {
+            return None;
+        }
+        if self.served.lock().unwrap().contains_key(&ino) {
+            return None;
+        }
+
+        let end = offset.saturating_add(u64::from(size)).min(file.size);
+        if offset >= end {
+            return Some(Vec::new());
+        }
+        let response = match self.send_request_with_range(url, "application/octet-stream", Some((offset, end))) {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("Range request for {} failed, falling back to a full download: {}", path, err);
+                return None;
+            }
+        };
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                debug!("Downloaded bytes {}..{} of {}", offset, end, path);
+                response.bytes().ok().map(|bytes| bytes.to_vec())
+            }
+            // The server ignored the Range: the response is the whole file
+            status if status.is_success() => {
+                let content = response.bytes().ok()?.to_vec();
+                let data = read_chunk(&content, offset as i64, size).to_vec();
+                self.contents.lock().unwrap().insert(key, content);
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true for files left out of listings by `--hide-binary`. Only
+    /// `--hide-binary-strict` downloads content to check for NUL bytes.
+    fn is_hidden_binary(&self, repo_id: Option<u64>, file: &GitHubFile) -> bool {
+        if file.file_type != "file" {
+            return false;
+        }
+        if let Some(binary) = repo_id.and_then(|repo_id| self.attribute_binary(repo_id, &file.path)) {
+            return binary;
+        }
+        if transform::has_binary_extension(&file.name) {
+            return true;
+        }
+        if !self.options.hide_binary_strict {
+            return false;
+        }
+        let full_name = repo_id.and_then(|repo_id| self.repos.read().unwrap().get(&repo_id).map(|repo| repo.full_name.clone()));
+        match full_name.map(|full_name| self.fetch_file_content(&full_name, &file.path)) {
+            Some(Ok(content)) => transform::is_binary(&content),
+            Some(Err(err)) => {
+                debug!("Failed to sample {}: {}", file.path, err);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Size of a file as served, when its content is already cached.
+    fn cached_size(&self, ino: u64) -> Option<u64> {
+        let (repo_id, path) = self.resolve(ino)?;
+        let full_name = self.repos.read().unwrap().get(&repo_id)?.full_name.clone();
+        let key = self.content_key(&full_name, &path);
+        let content = self.contents.lock().unwrap().get(&key)?;
+        Some(self.transform_content(Some((repo_id, &path)), content).len() as u64)
+    }
+
+    /// Returns the listing entry of a file inode, taken from its parent
+    /// directory's listing.
+    fn file_entry(&self, ino: u64) -> Option<GitHubFile> {
+        let (parent_ino, path) = self.parent_of(ino)?;
+        self.files.read().unwrap().get(&parent_ino)?.iter().find(|file| file.path == path).cloned()
+    }
+
+    /// Inode of the directory holding an entry, and the entry's path.
+    fn parent_of(&self, ino: u64) -> Option<(u64, String)> {
+        let (repo_id, path) = self.resolve(ino)?;
+        let parent = path.rsplit_once('/').map_or("", |(parent, _name)| parent);
+        let parent_ino = if parent.is_empty() {
+            repo_id
+        } else {
+            *self.inodes.read().unwrap().get(&(repo_id, parent.to_string()))?
+        };
+        Some((parent_ino, path))
+    }
+
+    /// Looks up the date of the last commit touching an entry and stores it
+    /// in the entry's listing, where `attr` finds it. Done once per entry.
+    fn load_commit_time(&self, ino: u64) {
+        if !self.options.commit_times || self.file_entry(ino).is_none_or(|file| file.commit_time.is_some()) {
+            return;
+        }
+        let (full_name, path) = match self.locate(ino) {
+            Some(location) => location,
+            None => return,
+        };
+        let backoff_key = format!("commits:{}/{}", self.current_full_name(&full_name), normalize_path(&path));
+        let time = match self.with_refetch_backoff(backoff_key, || self.fetch_commit_time(&full_name, &path)) {
+            // Without commits (empty repository) the epoch is kept
+            Ok(time) => time.unwrap_or(UNIX_EPOCH),
+            Err(err) => {
+                debug!("Failed to fetch the last commit of {}/{}: {}", full_name, path, err);
+                return;
+            }
+        };
+        if let Some((parent_ino, path)) = self.parent_of(ino) {
+            let mut files = self.files.write().unwrap();
+            if let Some(file) = files.get_mut(&parent_ino).and_then(|files| files.iter_mut().find(|file| file.path == path)) {
+                file.commit_time = Some(time);
+            }
+        }
+    }
+
+    /// Committer date of the last commit touching `path`, at the ref the
+    /// file is read from.
+    fn fetch_commit_time(&self, repo_full_name: &str, path: &str) -> io::Result<Option<SystemTime>> {
+        let branch = self.origin_branch(repo_full_name, path).or_else(|| self.options.reference.clone());
+        let pinned = if self.is_live(repo_full_name, path) { None } else { self.pinned_ref(repo_full_name, branch.as_deref()) };
+        let path = normalize_path(path);
+        let mut params = vec![("path", path.as_str()), ("per_page", "1")];
+        if let Some(git_ref) = pinned.as_deref().or(branch.as_deref()) {
+            params.push(("sha", git_ref));
+        }
+        let base = format!("{}/repos/{}/commits", self.api_url, self.current_full_name(repo_full_name));
+        let api_url = reqwest::Url::parse_with_params(&base, &params)
+            .map_err(|err| io::Error::other(format!("Invalid commits URL {}: {}", base, err)))?;
+        debug!("Fetching last commit from URL: {}", api_url);
+
+        let commits = self.get_json::<Vec<CommitSummary>>(api_url.as_str())?;
+        Ok(commits.first().and_then(|commit| parse_timestamp(&commit.commit.committer.date)))
+    }
+
+    /// Owners of an entry for `user.github.codeowners`, when enabled.
+    fn codeowners_xattr(&self, ino: u64) -> Option<String> {
+        if !self.options.with_codeowners {
+            return None;
+        }
+        let (repo_id, path) = self.resolve(ino)?;
+        self.repo_codeowners(repo_id)?.owners_for(&path).map(|owners| owners.join(" "))
+    }
+
+    /// Extended attributes of an entry taken from its listing, except the
+    /// CODEOWNERS one, which may need the file to be fetched.
+    fn xattrs(&self, ino: u64) -> Vec<(&'static str, String)> {
+        let mut xattrs = Vec::new();
+        if let Some(repo) = self.repos.read().unwrap().get(&ino) {
+            // Without a description the attribute doesn't exist (ENODATA)
+            if let Some(description) = &repo.description {
+                xattrs.push((XATTR_DESCRIPTION, description.clone()));
+            }
+            return xattrs;
+        }
+        let entry = match self.file_entry(ino) {
+            Some(entry) => entry,
+            None => return xattrs,
+        };
+        if !entry.sha.is_empty() {
+            xattrs.push((XATTR_SHA, entry.sha.clone()));
+        }
+        xattrs.push((XATTR_PATH, entry.path.clone()));
+        if let Some(download_url) = &entry.download_url {
+            xattrs.push((XATTR_DOWNLOAD_URL, download_url.clone()));
+        }
+        if !self.options.union_branches.is_empty() {
+            if let Some((repo_id, path)) = self.resolve(ino) {
+                let full_name = self.repos.read().unwrap().get(&repo_id).map(|repo| repo.full_name.clone());
+                if let Some(branch) = full_name.and_then(|full_name| self.origin_branch(&full_name, &path)) {
+                    xattrs.push((XATTR_BRANCH, branch));
+                }
+            }
+        }
+        xattrs
+    }
+
+    /// Link count: 1 for files and 2 plus the number of subdirectories for
+    /// directories. Listings not loaded yet count as having none, so that
+    /// `getattr` never fetches.
+    fn nlink(&self, ino: u64, kind: FileType) -> u32 {
+        if kind != FileType::Directory {
+            return 1;
+        }
+        let subdirs = if ino == 1 {
+            self.repos.read().unwrap().len()
+        } else {
+            self.files.read().unwrap()
+                .get(&ino)
+                .map_or(0, |files| files.iter().filter(|file| file.kind() == FileType::Directory).count())
+        };
+        2 + subdirs as u32
+    }
+
+    fn attr(&self, ino: u64) -> io::Result<FileAttr> {
+        let is_meta_dir = self.synthetic_entry(ino).is_some_and(|(_repo, name)| name == META_DIR_NAME);
+        let entry = self.file_entry(ino);
+        let kind = if ino == 1 || is_meta_dir || self.repos.read().unwrap().contains_key(&ino) {
+            FileType::Directory
+        } else {
+            entry.as_ref().map_or(FileType::RegularFile, GitHubFile::kind)
+        };
+        let time = entry.as_ref().and_then(|file| file.commit_time).unwrap_or(UNIX_EPOCH);
+        let perm = match (kind, &entry) {
+            (FileType::Directory, _) => 0o755,
+            (_, Some(file)) => file.perm(),
+            // Synthetic files are not executable
+            (_, None) => 0o644,
+        };
+        let size = match self.listing_dir(ino) {
+            Some(dir) => self.listing_json(dir).map_or(0, |json| json.len() as u64),
+            None => self.marker_message(ino)
+                .map(|message| message.len() as u64)
+                .or_else(|| self.synthetic_content(ino).map(|content| content.len() as u64))
+                .or_else(|| self.cached_size(ino))
+                // Directories have size 0
+                .or_else(|| entry.filter(|file| file.file_type != "dir").map(|file| file.size))
+                .unwrap_or(0),
+        };
+
+        Ok(FileAttr {
+            ino,
+            size,
+            blocks: 1,
+            atime: time,
+            mtime: time,
+            ctime: time,
+            crtime: time,
+            kind,
+            perm,
+            nlink: self.nlink(ino, kind),
+            uid: self.options.uid,
+            gid: self.options.gid,
+            rdev: 0,
+            blksize: 512,
+            flags: 0,
+        })
+    }
+
+    /// Entries of a directory, `.` and `..` first. `first_call` marks the
+    /// start of a scan, when default branches and live listings refresh.
+    fn directory_entries(&self, ino: u64, first_call: bool) -> io::Result<Vec<DirEntry>> {
+        if first_call && ino == 1 && self.options.follow_default_branch_changes {
+            self.refresh_default_branches();
+        }
+
+        let mut children = match self.directory_listing(ino) {
+            Ok(children) => children,
+            // Repositories with a marker list only the marker
+            Err(err) if self.repo_marker(ino).is_some() => {
+                debug!("Listing the marker of repository {}: {}", ino, err);
+                None
+            }
+            Err(err) => return Err(err),
+        };
+        if first_call && children.is_some() {
+            if let Some(files) = self.refetch_live_listing(ino) {
+                children = Some(files);
+            }
+        }
+
+        let mut entries: Vec<DirEntry> = vec![
+            (ino, FileType::Directory, ".".to_string()),
+            (ino, FileType::Directory, "..".to_string()),
+        ];
+
+        if let Some(marker) = self.repo_marker(ino) {
+            entries.push((marker.inode, FileType::RegularFile, marker.name.to_string()));
+        } else if let Some((repo_id, META_DIR_NAME)) = self.synthetic_entry(ino) {
+            for (name, _content) in self.meta_files(repo_id) {
+                entries.push((self.synthetic_inode(repo_id, name), FileType::RegularFile, name.to_string()));
+            }
+        } else if ino == 1 {
+            // Stable order across calls with an offset
+            let mut repos: Vec<_> = self.repos.read().unwrap().iter().map(|(inode, repo)| (*inode, repo.name.clone())).collect();
+            repos.sort_by_key(|(inode, _name)| *inode);
+            entries.push((self.synthetic_inode(1, STATUS_NAME), FileType::RegularFile, STATUS_NAME.to_string()));
+            for (inode, name) in repos {
+                entries.push((inode, FileType::Directory, name));
+            }
+        } else if let Some(files) = children {
+            // Work on a copy so the lock isn't held during the reply
+            let mut files = files;
+
+            if self.options.hide_binary || self.options.hide_binary_strict {
+                let repo_id = self.repo_of(ino);
+                files.retain(|file| !self.is_hidden_binary(repo_id, file));
+            }
+
+            if self.options.preopen_readme && self.repos.read().unwrap().contains_key(&ino) {
+                // sort_by_key is stable, so the rest keeps the original order
+                files.sort_by_key(|file| !is_readme(&file.name));
+            }
+
+            if self.options.with_json_listing {
+                entries.push((self.listing_inode(ino), FileType::RegularFile, JSON_LISTING_NAME.to_string()));
+            }
+            if self.options.with_sizes {
+                entries.push((self.synthetic_inode(ino, SIZES_NAME), FileType::RegularFile, SIZES_NAME.to_string()));
+            }
+            if self.meta_enabled() && self.repos.read().unwrap().contains_key(&ino) {
+                entries.push((self.synthetic_inode(ino, META_DIR_NAME), FileType::Directory, META_DIR_NAME.to_string()));
+            }
+            if self.description_text(ino).is_some() {
+                entries.push((self.synthetic_inode(ino, DESCRIPTION_NAME), FileType::RegularFile, DESCRIPTION_NAME.to_string()));
+            }
+
+            if self.is_collapsed(ino, &files) {
+                debug!("readdir(ino: {}) collapsed beyond max display depth", ino);
+                entries.push((self.synthetic_inode(ino, DEEP_PLACEHOLDER_NAME), FileType::RegularFile, DEEP_PLACEHOLDER_NAME.to_string()));
+            } else {
+                let repo_id = self.repo_of(ino).unwrap_or(ino);
+                for file in &files {
+                    entries.push((self.path_inode(repo_id, &file.path), file.kind(), file.name.clone()));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Checks an `access` mask against the entry's mode bits the way the
+    /// kernel does with `default_permissions`. Writing is never allowed.
+    fn check_access(&self, ino: u64, uid: u32, gid: u32, mask: i32) -> Result<(), c_int> {
+        if mask & libc::W_OK != 0 {
+            return Err(EROFS);
+        }
+        let attr = self.attr(ino).map_err(|err| errno_for(&err))?;
+        let perm = u32::from(attr.perm);
+        let granted = if uid == 0 {
+            // Root reads everything, but only executes if some execute bit is set
+            let exec = if attr.kind == FileType::Directory || perm & 0o111 != 0 { libc::X_OK } else { 0 };
+            libc::R_OK | exec
+        } else if uid == attr.uid {
+            (perm >> 6 & 0o7) as i32
+        } else if gid == attr.gid {
+            (perm >> 3 & 0o7) as i32
+        } else {
+            (perm & 0o7) as i32
+        };
+        let wanted = mask & (libc::R_OK | libc::X_OK);
+        if wanted & granted == wanted {
+            Ok(())
+        } else {
+            Err(EACCES)
+        }
+    }
+}
+
+impl Filesystem for GitHubFS {
+    fn init(&mut self, _req: &Request<'_>, config: &mut KernelConfig) -> Result<(), libc::c_int> {
+        info!("GitHubFS initialized for {}", self.username);
+        self.tune_kernel_config(config);
+
+        if let (Some(interval), true) = (self.options.keepalive_interval, self.is_authenticated()) {
+            spawn_keepalive(self.client.clone(), self.api_url.clone(), self.options.api_version.clone(), self.shared_token(), interval);
+        }
+        if let Some(interval) = self.options.refresh_interval {
+            spawn_refresh(interval);
+        }
+        Ok(())
+    }
+
+    fn destroy(&mut self) {
+        info!(
+            "GitHubFS for {} shutting down after {} requests ({} cache hits, {} misses)",
+            self.username,
+            self.metrics.requests.load(Ordering::Relaxed),
+            self.metrics.cache_hits.load(Ordering::Relaxed),
+            self.metrics.cache_misses.load(Ordering::Relaxed),
+        );
+        self.persist_cache();
+        self.contents.lock().unwrap().clear();
+        self.prefixes.write().unwrap().clear();
+        self.served.lock().unwrap().clear();
+        self.etags.lock().unwrap().clear();
+    }
+
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        debug!("lookup(parent: {}, name: {:?})", parent, name);
+        self.apply_control_requests();
+
+        if self.is_known_missing(parent, name) {
+            debug!("lookup(parent: {}, name: {:?}) answered from the negative cache", parent, name);
+            reply.error(ENOENT);
+            return;
+        }
+
+        if parent == 1 {
+            // Diretório raiz, procura pelo repositórios
+            let found = self.repos.read().unwrap()
+                .iter()
+                .find(|(_inode, repo)| OsStr::new(&repo.name) == name)
+                .map(|(&inode, _repo)| inode);
+            if let Some(inode) = found {
+                reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                return;
+            }
+            if name == STATUS_NAME {
+                let inode = self.synthetic_inode(1, STATUS_NAME);
+                reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                return;
+            }
+        } else {
+            if let Some(marker) = self.repo_marker(parent) {
+                if name == marker.name {
+                    reply.entry(&Duration::new(1, 0), &self.attr(marker.inode).unwrap(), self.generation(marker.inode));
+                } else {
+                    reply.error(EACCES);
+                }
+                return;
+            }
+
+            if name == DESCRIPTION_NAME && self.description_text(parent).is_some() {
+                let inode = self.synthetic_inode(parent, DESCRIPTION_NAME);
+                reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                return;
+            }
+            if self.meta_enabled() && name == META_DIR_NAME && self.repos.read().unwrap().contains_key(&parent) {
+                let inode = self.synthetic_inode(parent, META_DIR_NAME);
+                reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                return;
+            }
+            if let Some((repo_id, META_DIR_NAME)) = self.synthetic_entry(parent) {
+                let found = self.meta_files(repo_id)
+                    .into_iter()
+                    .find(|(file_name, _content)| name == *file_name);
+                match found {
+                    Some((file_name, _content)) => {
+                        let inode = self.synthetic_inode(repo_id, file_name);
+                        reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                    }
+                    None => reply.error(ENOENT),
+                }
+                return;
+            }
+
+            // Procura arquivos em repositórios
+            let files = match self.directory_listing(parent) {
+                Ok(files) => files,
+                Err(err) => {
+                    error!("Failed to load directory {}: {}", parent, err);
+                    reply.error(errno_for(&err));
+                    return;
+                }
+            };
+            if let Some(files) = files {
+                if self.options.with_json_listing && name == JSON_LISTING_NAME {
+                    let inode = self.listing_inode(parent);
+                    reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                    return;
+                }
+                if self.options.with_sizes && name == SIZES_NAME {
+                    let inode = self.synthetic_inode(parent, SIZES_NAME);
+                    reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                    return;
+                }
+                if name == DEEP_PLACEHOLDER_NAME && self.is_collapsed(parent, &files) {
+                    let inode = self.synthetic_inode(parent, DEEP_PLACEHOLDER_NAME);
+                    reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                    return;
+                }
+                // Works at any level: the child's inode comes from the full path
+                if let Some(inode) = self.find_child(parent, &files, name) {
+                    reply.entry(&Duration::new(1, 0), &self.attr(inode).unwrap(), self.generation(inode));
+                    return;
+                }
+            }
+        }
+
+        self.remember_missing(parent, name);
+        reply.error(ENOENT);
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        debug!("getattr(ino: {})", ino);
+        self.apply_control_requests();
+
+        self.load_commit_time(ino);
+        match self.attr(ino) {
+            Ok(attr) => reply.attr(&Duration::new(1, 0), &attr),
+            Err(err) => reply.error(errno_for(&err)),
+        }
+    }
+
+    fn opendir(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        debug!("opendir(ino: {}, flags: {:#x})", ino, flags);
+        self.apply_control_requests();
+
+        // Snapshot the listing so that a refresh in the middle of the scan
+        // doesn't shift the offsets
+        match self.directory_entries(ino, true) {
+            Ok(entries) => {
+                let fh = self.next_handle.fetch_add(1, Ordering::Relaxed);
+                self.handles.lock().unwrap().insert(fh, entries);
+                reply.opened(fh, 0);
+            }
+            Err(err) => {
+                error!("Failed to load directory {}: {}", ino, err);
+                reply.error(errno_for(&err));
+            }
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request, ino: u64, fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        debug!("readdir(ino: {}, fh: {}, offset: {})", ino, fh, offset);
+        self.apply_control_requests();
+
+        let snapshot = self.handles.lock().unwrap().get(&fh).cloned();
+        let entries = match snapshot {
+            Some(entries) => entries,
+            // Without opendir, build the listing on every call
+            None => match self.directory_entries(ino, offset == 0) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    error!("Failed to load directory {}: {}", ino, err);
+                    reply.error(errno_for(&err));
+                    return;
+                }
+            },
+        };
+
+        fill_directory(&entries, offset, |inode, next, kind, name| reply.add(inode, next, kind, name));
+        reply.ok();
+    }
+
+    fn releasedir(&mut self, _req: &Request<'_>, ino: u64, fh: u64, _flags: i32, reply: ReplyEmpty) {
+        debug!("releasedir(ino: {}, fh: {})", ino, fh);
+        self.handles.lock().unwrap().remove(&fh);
+        reply.ok();
+    }
+
+    fn access(&mut self, req: &Request<'_>, ino: u64, mask: i32, reply: ReplyEmpty) {
+        debug!("access(ino: {}, mask: {:#o})", ino, mask);
+
+        match self.check_access(ino, req.uid(), req.gid(), mask) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        debug!("open(ino: {}, flags: {:#x})", ino, flags);
+
+        match check_open_flags(flags) {
+            Ok(()) => reply.opened(0, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        debug!("read(ino: {}, offset: {}, size: {})", ino, offset, size);
+        self.apply_control_requests();
+
+        if offset < 0 {
+            reply.error(EINVAL);
+            return;
+        }
+
+        let synthetic = match self.listing_dir(ino) {
+            Some(dir) => Some(self.listing_json(dir).unwrap_or_default()),
+            None => self.marker_message(ino)
+                .map(String::into_bytes)
+                .or_else(|| self.synthetic_content(ino)),
+        };
+        if let Some(content) = synthetic {
+            reply.data(read_chunk(&content, offset, size));
+            return;
+        }
+
+        if let (Some(file), Some((full_name, path))) = (self.file_entry(ino), self.locate(ino)) {
+            // Submodules and entries from the trees API have no
+            // download_url, but are read through the contents API
+            if file.file_type != "dir" {
+                if let Some(data) = self.read_prefix(ino, &file, offset as u64, size) {
+                    reply.data(&data);
+                    return;
+                }
+                if let Some(data) = self.read_range(ino, &file, offset as u64, size) {
+                    reply.data(&data);
+                    return;
+                }
+                match self.served_content(ino, offset, &full_name, &path) {
+                    Ok(content) => reply.data(read_chunk(&content, offset, size)),
+                    Err(err) => {
+                        error!("Failed to fetch {}/{}: {}", full_name, path, err);
+                        reply.error(errno_for(&err));
+                    }
+                }
+            } else {
+                reply.error(EISDIR);
+            }
+            return;
+        }
+
+        reply.error(ENOENT);
+    }
+
+    fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+        debug!("readlink(ino: {})", ino);
+        self.apply_control_requests();
+
+        let entry = match self.file_entry(ino) {
+            Some(entry) if entry.file_type == "symlink" => entry,
+            Some(_) => {
+                reply.error(EINVAL);
+                return;
+            }
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+        if let Some(target) = &entry.target {
+            reply.data(target.as_bytes());
+            return;
+        }
+        // Listings don't carry the target; it is the content of the blob
+        match self.locate(ino).map(|(full_name, path)| self.fetch_file_content(&full_name, &path)) {
+            Some(Ok(target)) => reply.data(&target),
+            Some(Err(err)) => {
+                error!("Failed to read symlink {}: {}", entry.path, err);
+                reply.error(errno_for(&err));
+            }
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _offset: i64,
+        _data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        debug!("write(ino: {}) refused on a read-only filesystem", ino);
+        reply.error(EROFS);
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        debug!("create(parent: {}, name: {:?}) refused on a read-only filesystem", parent, name);
+        reply.error(EROFS);
+    }
+
+    fn mkdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, _mode: u32, _umask: u32, reply: ReplyEntry) {
+        debug!("mkdir(parent: {}, name: {:?}) refused on a read-only filesystem", parent, name);
+        reply.error(EROFS);
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        debug!("unlink(parent: {}, name: {:?}) refused on a read-only filesystem", parent, name);
+        reply.error(EROFS);
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        debug!("rmdir(parent: {}, name: {:?}) refused on a read-only filesystem", parent, name);
+        reply.error(EROFS);
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _newparent: u64,
+        _newname: &OsStr,
+        _flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        debug!("rename(parent: {}, name: {:?}) refused on a read-only filesystem", parent, name);
+        reply.error(EROFS);
+    }
+
+    fn statfs(&mut self, _req: &Request<'_>, _ino: u64, reply: ReplyStatfs) {
+        debug!("statfs()");
+
+        // Only counts what was already listed; the rest isn't known yet
+        let (entries, used_bytes) = self.files.read().unwrap()
+            .values()
+            .flatten()
+            .fold((0u64, 0u64), |(entries, bytes), file| {
+                let size = if file.file_type == "dir" { 0 } else { file.size };
+                (entries + 1, bytes + size)
+            });
+        let inodes = entries + self.repos.read().unwrap().len() as u64 + 1;
+        let used = used_bytes.div_ceil(STATFS_BLOCK_SIZE as u64);
+        let blocks = STATFS_TOTAL_BLOCKS.max(used);
+        let free = blocks - used;
+        reply.statfs(blocks, free, free, inodes, 0, STATFS_BLOCK_SIZE, 255, STATFS_BLOCK_SIZE);
+    }
+
+    fn getxattr(&mut self, _req: &Request<'_>, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        debug!("getxattr(ino: {}, name: {:?}, size: {})", ino, name, size);
+
+        let value = if name == XATTR_CODEOWNERS {
+            self.codeowners_xattr(ino)
+        } else {
+            self.xattrs(ino)
+                .into_iter()
+                .find(|(xattr, _value)| name == *xattr)
+                .map(|(_xattr, value)| value)
+        };
+        match value {
+            Some(value) => reply_xattr(reply, size, value.as_bytes()),
+            None => reply.error(ENODATA),
+        }
+    }
+
+    fn listxattr(&mut self, _req: &Request<'_>, ino: u64, size: u32, reply: ReplyXattr) {
+        debug!("listxattr(ino: {}, size: {})", ino, size);
+
+        // NUL-terminated names, one after the other
+        let mut names = Vec::new();
+        if self.codeowners_xattr(ino).is_some() {
+            names.extend_from_slice(XATTR_CODEOWNERS.as_bytes());
+            names.push(0);
+        }
+        for (name, _value) in self.xattrs(ino) {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        reply_xattr(reply, size, &names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    const SHA: &str = "7fd1a60b01f91b314f59955a4e4d4e80d8edf11d";
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Missing fixture {}: {}", path, err))
+    }
+
+    /// Filesystem talking to the mock server, with nothing loaded yet.
+    fn mock_fs(server: &Server, token: Option<&str>) -> GitHubFS {
+        let options = FsOptions {
+            api_url: Some(server.url()),
+            api_version: Some(DEFAULT_API_VERSION.to_string()),
+            cache_size: DEFAULT_CACHE_SIZE_MB * 1024 * 1024,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            ..FsOptions::default()
+        };
+        GitHubFS::unloaded("octocat".to_string(), token.map(str::to_string), options).unwrap()
+    }
+
+    fn add_hello_world(fs: &GitHubFS) -> u64 {
+        let repos: Vec<GitHubRepository> = serde_json::from_str(&fixture("user_repos.json")).unwrap();
+        fs.add_repository(repos.into_iter().next().unwrap())
+    }
+
+    /// Pins HEAD of `octocat/hello-world` to `SHA`.
+    fn mock_head(server: &mut Server) -> mockito::Mock {
+        server.mock("GET", "/repos/octocat/hello-world/commits/HEAD")
+            .with_body(SHA)
+            .create()
+    }
+
+    #[test]
+    fn build_client_trusts_a_valid_ca_certificate() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let with_ca = |path: PathBuf| build_client(&FsOptions { ca_cert: Some(path), ..FsOptions::default() });
+
+        assert!(with_ca(fixtures.join("ca.pem")).is_ok());
+        assert_eq!(with_ca(fixtures.join("user.json")).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(with_ca(fixtures.join("missing.pem")).unwrap_err().kind(), ErrorKind::NotFound);
+        assert!(build_client(&FsOptions { insecure: true, ..FsOptions::default() }).is_ok());
+    }
+
+    #[test]
+    fn fetch_repositories_lists_own_repos_with_token() {
+        let mut server = Server::new();
+        let user = server.mock("GET", "/user")
+            .match_header("authorization", "Bearer secret")
+            .match_header("x-github-api-version", DEFAULT_API_VERSION)
+            .match_header("accept", GITHUB_JSON)
+            .with_body(fixture("user.json"))
+            .create();
+        let repos = server.mock("GET", "/user/repos")
+            .match_query(Matcher::UrlEncoded("per_page".into(), PER_PAGE.to_string()))
+            .with_body(fixture("user_repos.json"))
+            .create();
+
+        let fs = mock_fs(&server, Some("secret"));
+        let names: Vec<String> = fs.fetch_repositories().unwrap().into_iter().map(|repo| repo.full_name).collect();
+
+        assert_eq!(names, ["octocat/hello-world", "octocat/spoon-knife"]);
+        user.assert();
+        repos.assert();
+    }
+
+    #[test]
+    fn fetch_repositories_of_another_owner_uses_the_stored_login() {
+        let mut server = Server::new();
+        let user = server.mock("GET", "/user").expect(0).create();
+        let repos = server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(fixture("user_repos.json"))
+            .create();
+
+        let mut fs = mock_fs(&server, Some("secret"));
+        fs.login = Some("hubot".to_string());
+
+        assert_eq!(fs.fetch_repositories().unwrap().len(), 2);
+        user.assert();
+        repos.assert();
+    }
+
+    #[test]
+    fn fetch_repositories_stops_at_max_repos_after_filtering() {
+        let mut server = Server::new();
+        let next = format!("{}/users/octocat/repos?per_page={}&page=2", server.url(), PER_PAGE);
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Exact(format!("per_page={}", PER_PAGE)))
+            .with_header("Link", &format!("<{}>; rel=\"next\"", next))
+            .with_body(fixture("user_repos.json"))
+            .create();
+        let second_page = server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body("[]")
+            .expect(0)
+            .create();
+
+        let mut fs = mock_fs(&server, None);
+        fs.options.max_repos = Some(1);
+        fs.options.exclude = vec!["hello-*".to_string()];
+        let names: Vec<String> = fs.fetch_repositories().unwrap().into_iter().map(|repo| repo.name).collect();
+
+        assert_eq!(names, ["spoon-knife"]);
+        second_page.assert();
+    }
+
+    #[test]
+    fn fetch_repositories_lists_public_repos_without_token() {
+        let mut server = Server::new();
+        let repos = server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(fixture("user_repos.json"))
+            .create();
+
+        let fs = mock_fs(&server, None);
+
+        assert_eq!(fs.fetch_repositories().unwrap().len(), 2);
+        repos.assert();
+    }
+
+    #[test]
+    fn fetch_repositories_reports_bad_credentials() {
+        let mut server = Server::new();
+        server.mock("GET", "/user")
+            .with_status(401)
+            .with_body(r#"{"message":"Bad credentials"}"#)
+            .create();
+
+        let err = mock_fs(&server, Some("expired")).fetch_repositories().unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(errno_for(&err), EACCES);
+    }
+
+    #[test]
+    fn verify_token_explains_a_rejected_token() {
+        let mut server = Server::new();
+        server.mock("GET", "/user")
+            .with_status(401)
+            .with_body(r#"{"message":"Bad credentials"}"#)
+            .create();
+
+        let err = mock_fs(&server, Some("expired")).verify_token().unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(err.to_string().starts_with("Invalid or expired GitHub token"));
+    }
+
+    #[test]
+    fn verify_token_is_skipped_without_token() {
+        let mut server = Server::new();
+        let user = server.mock("GET", "/user").expect(0).create();
+
+        assert_eq!(mock_fs(&server, None).verify_token().unwrap(), None);
+        user.assert();
+    }
+
+    #[test]
+    fn fail_on_rate_limit_aborts_startup_instead_of_waiting() {
+        let mut server = Server::new();
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(fixture("user_repos.json"))
+            .create();
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 3600;
+        server.mock("GET", Matcher::Regex(r"^/repos/".into()))
+            .with_status(403)
+            .with_header("X-RateLimit-Remaining", "0")
+            .with_header("X-RateLimit-Reset", &reset.to_string())
+            .with_body(r#"{"message": "API rate limit exceeded"}"#)
+            .create();
+        let options = FsOptions {
+            api_url: Some(server.url()),
+            cache_size: DEFAULT_CACHE_SIZE_MB * 1024 * 1024,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            warm_on_mount: true,
+            fail_on_rate_limit: true,
+            ..FsOptions::default()
+        };
+
+        let started = Instant::now();
+        let err = GitHubFS::new("octocat".to_string(), None, options).err().unwrap();
+        assert!(is_rate_limited(&err));
+        assert_eq!(errno_for(&err), EAGAIN);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn identical_blobs_are_downloaded_once() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex(r"^/repos/octocat/[a-z-]+/commits/".into()))
+            .with_status(404)
+            .create();
+        let license = serde_json::json!([{"name": "LICENSE", "path": "LICENSE", "type": "file", "size": 4, "sha": "1a2b3c"}]).to_string();
+        let mut downloads = Vec::new();
+        for name in ["hello-world", "spoon-knife"] {
+            server.mock("GET", format!("/repos/octocat/{}/contents/", name).as_str())
+                .match_query(Matcher::Any)
+                .with_body(&license)
+                .create();
+            downloads.push(server.mock("GET", format!("/repos/octocat/{}/contents/LICENSE", name).as_str())
+                .match_query(Matcher::Any)
+                .with_body(serde_json::json!({"type": "file", "encoding": "base64", "content": BASE64.encode("MIT\n")}).to_string())
+                .expect(if name == "hello-world" { 1 } else { 0 })
+                .create());
+        }
+        let fs = mock_fs(&server, None);
+
+        for name in ["octocat/hello-world", "octocat/spoon-knife"] {
+            fs.fetch_directory(name, "").unwrap();
+            assert_eq!(fs.fetch_file_content(name, "LICENSE").unwrap(), b"MIT\n");
+        }
+        for download in downloads {
+            download.assert();
+        }
+    }
+
+    #[test]
+    fn control_socket_refreshes_listings_and_reloads_the_token() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let server = Server::new();
+        let fs = mock_fs(&server, Some("original"));
+        let repo_id = add_hello_world(&fs);
+        fs.store_listing(repo_id, repo_id, &entries(&[("README.md", "file")]));
+        let dir = std::env::temp_dir().join(format!("githubfs-control-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("token"), "rotated\n").unwrap();
+        let shared = control::Shared {
+            metrics: fs.shared_metrics(),
+            token: fs.shared_token(),
+            token_file: Some(dir.join("token")),
+        };
+        control::spawn(&dir.join("control.sock"), shared).unwrap();
+
+        let mut stream = UnixStream::connect(dir.join("control.sock")).unwrap();
+        let mut replies = BufReader::new(stream.try_clone().unwrap()).lines();
+        let mut send = |command: &str| {
+            writeln!(stream, "{}", command).unwrap();
+            replies.next().unwrap().unwrap()
+        };
+
+        assert_eq!(send("refresh"), "ok refresh scheduled");
+        assert!(fs.files.read().unwrap().contains_key(&repo_id));
+        fs.apply_control_requests();
+        assert!(!fs.files.read().unwrap().contains_key(&repo_id));
+
+        assert_eq!(send("reload-token"), "ok token reloaded");
+        assert_eq!(fs.token.read().unwrap().as_deref(), Some("rotated"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resolve_maps_a_nested_file_to_its_repository_and_path() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let other_id = fs.add_repository(repos_named(&["api"]).remove(0));
+        fs.store_listing(repo_id, repo_id, &entries(&[("src", "dir")]));
+        let src = fs.path_inode(repo_id, "src");
+        fs.store_listing(repo_id, src, &entries(&[("src/lib", "dir")]));
+        let lib = fs.path_inode(repo_id, "src/lib");
+        fs.store_listing(repo_id, lib, &entries(&[("src/lib/mod.rs", "file")]));
+        fs.store_listing(other_id, other_id, &entries(&[("src", "dir")]));
+
+        let file = fs.path_inode(repo_id, "src/lib/mod.rs");
+        assert_eq!(fs.resolve(file), Some((repo_id, "src/lib/mod.rs".to_string())));
+        assert_eq!(fs.resolve(lib), Some((repo_id, "src/lib".to_string())));
+        assert_eq!(fs.resolve(repo_id), Some((repo_id, String::new())));
+        assert_ne!(fs.path_inode(other_id, "src"), src);
+        assert_eq!(fs.resolve(fs.path_inode(other_id, "src")), Some((other_id, "src".to_string())));
+    }
+
+    #[test]
+    fn responses_over_the_size_limit_are_rejected() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/empty")
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.max_response_size = 64;
+
+        let err = fs.fetch_directory("octocat/hello-world", "").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("exceeds the limit of 64 bytes"), "{}", err);
+        assert!(fs.fetch_directory("octocat/hello-world", "empty").unwrap().is_empty());
+    }
+
+    #[test]
+    fn paths_are_normalized_to_canonical_form() {
+        assert_eq!(normalize_path("/src/main.rs"), "src/main.rs");
+        assert_eq!(normalize_path("src/lib/"), "src/lib");
+        assert_eq!(normalize_path("src//lib///mod.rs"), "src/lib/mod.rs");
+        assert_eq!(normalize_path("//"), "");
+        assert_eq!(join_path("src/", "/main.rs"), "src/main.rs");
+        assert_eq!(join_path("", "README.md"), "README.md");
+
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let expected = format!("{}/repos/octocat/hello-world/contents/src/lib/mod.rs", server.url());
+        assert_eq!(fs.contents_url("octocat/hello-world", "/src//lib/mod.rs/"), expected);
+        assert_eq!(fs.contents_url("octocat/hello-world", "/"), format!("{}/repos/octocat/hello-world/contents/", server.url()));
+    }
+
+    #[test]
+    fn refs_with_special_characters_are_encoded_in_the_query() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex("^/repos/octocat/hello-world/commits/".to_string()))
+            .with_status(404)
+            .create();
+        let readme = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::UrlEncoded("ref".into(), "fix/a#b+c&d".into()))
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.reference = Some("fix/a#b+c&d".to_string());
+
+        assert_eq!(with_ref("https://api.github.com/x".to_string(), None).unwrap(), "https://api.github.com/x");
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        readme.assert();
+    }
+
+    /// Records what `tune_kernel_config` sets, capping `max_background` like
+    /// a kernel that rejects large values.
+    #[derive(Default)]
+    struct FakeKernel {
+        capabilities: u32,
+        max_background: u16,
+        congestion_threshold: u16,
+    }
+
+    impl KernelTuning for FakeKernel {
+        fn add_capabilities(&mut self, capabilities: u32) -> Result<(), u32> {
+            self.capabilities |= capabilities;
+            Ok(())
+        }
+
+        fn set_max_background(&mut self, value: u16) -> Result<u16, u16> {
+            if value > 256 {
+                return Err(256);
+            }
+            Ok(std::mem::replace(&mut self.max_background, value))
+        }
+
+        fn set_congestion_threshold(&mut self, value: u16) -> Result<u16, u16> {
+            Ok(std::mem::replace(&mut self.congestion_threshold, value))
+        }
+    }
+
+    #[test]
+    fn init_tunes_the_kernel_for_concurrent_reads() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.max_background = DEFAULT_MAX_BACKGROUND;
+        fs.options.congestion_threshold = DEFAULT_CONGESTION_THRESHOLD;
+        let mut kernel = FakeKernel::default();
+
+        fs.tune_kernel_config(&mut kernel);
+        assert_ne!(kernel.capabilities & fuser::consts::FUSE_ASYNC_READ, 0);
+        assert_eq!(kernel.max_background, DEFAULT_MAX_BACKGROUND);
+        assert_eq!(kernel.congestion_threshold, DEFAULT_CONGESTION_THRESHOLD);
+
+        fs.options.max_background = 1000;
+        fs.tune_kernel_config(&mut kernel);
+        assert_eq!(kernel.max_background, 256);
+    }
+
+    #[test]
+    fn submodules_serve_their_commit_and_url_when_enabled() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/vendor/lib")
+            .match_query(Matcher::Any)
+            .with_body(r#"{"type": "submodule", "name": "lib", "path": "vendor/lib", "sha": "a1b2c3d4", "submodule_git_url": "https://github.com/octocat/lib.git"}"#)
+            .create();
+        let mut fs = mock_fs(&server, None);
+
+        assert!(fs.download_git_content("octocat/hello-world", "vendor/lib").unwrap().is_empty());
+        fs.options.include_submodule_gitlink_content = true;
+        assert_eq!(
+            fs.download_git_content("octocat/hello-world", "vendor/lib").unwrap(),
+            b"commit a1b2c3d4\nurl https://github.com/octocat/lib.git\n",
+        );
+    }
+
+    #[test]
+    fn synthetic_inodes_are_stable_and_skip_taken_inodes() {
+        let server = Server::new();
+        let inodes = || {
+            let fs = mock_fs(&server, None);
+            let repo_id = add_hello_world(&fs);
+            (fs.synthetic_inode(repo_id, META_DIR_NAME), fs.synthetic_inode(repo_id, SIZES_NAME))
+        };
+        let first = inodes();
+        assert_eq!(inodes(), first);
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let listing = fs.listing_inode(repo_id);
+        assert_eq!(fs.listing_dir(listing), Some(repo_id));
+        assert_ne!(listing, first.0);
+        assert!(first.0 >= SYNTHETIC_INODE_BASE && first.1 >= SYNTHETIC_INODE_BASE);
+        assert_ne!(first.0, first.1);
+
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let meta = synthetic_inode_for(repo_id, META_DIR_NAME);
+        let status = synthetic_inode_for(repo_id, STATUS_NAME);
+        fs.synthetic.write().unwrap().insert((repo_id + 1, META_DIR_NAME), meta);
+        fs.paths.write().unwrap().insert(status, (repo_id, "taken".to_string()));
+        assert_eq!(fs.synthetic_inode(repo_id, META_DIR_NAME), next_synthetic_inode(meta));
+        assert_eq!(fs.synthetic_inode(repo_id, STATUS_NAME), next_synthetic_inode(status));
+        assert_eq!(next_synthetic_inode(u64::MAX), SYNTHETIC_INODE_BASE);
+    }
+
+    #[test]
+    fn refetch_backoff_grows_between_failing_requests() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let flaky = server.mock("GET", "/repos/octocat/hello-world/contents/flaky.txt")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .expect(3)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.refetch_interval_on_error = Some(Duration::from_millis(100));
+        let fetch = || fs.fetch_file_content("octocat/hello-world", "flaky.txt").unwrap_err().kind();
+
+        assert_ne!(fetch(), ErrorKind::WouldBlock);
+        assert_eq!(fetch(), ErrorKind::WouldBlock);
+        thread::sleep(Duration::from_millis(150));
+        assert_ne!(fetch(), ErrorKind::WouldBlock);
+        // The second failure doubled the wait to 200 ms
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(fetch(), ErrorKind::WouldBlock);
+        thread::sleep(Duration::from_millis(100));
+        assert_ne!(fetch(), ErrorKind::WouldBlock);
+        flaky.assert();
+    }
+
+    #[test]
+    fn description_file_is_served_only_for_described_repositories() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.with_descriptions = true;
+        let repos: Vec<GitHubRepository> = serde_json::from_str(&fixture("user_repos.json")).unwrap();
+        let repo_ids: Vec<u64> = repos.into_iter().map(|repo| fs.add_repository(repo)).collect();
+        let names = |ino: u64| -> Vec<String> {
+            fs.store_listing(ino, ino, &[]);
+            fs.directory_entries(ino, true).unwrap().into_iter().map(|(_ino, _kind, name)| name).collect()
+        };
+
+        let (described, undescribed) = (repo_ids[0], repo_ids[1]);
+        assert!(names(described).contains(&DESCRIPTION_NAME.to_string()));
+        let ino = fs.synthetic_inode(described, DESCRIPTION_NAME);
+        assert_eq!(fs.synthetic_content(ino).unwrap(), b"My first repository\n");
+        assert_eq!(fs.attr(ino).unwrap().size, 20);
+        assert!(!names(undescribed).contains(&DESCRIPTION_NAME.to_string()));
+        assert_eq!(fs.description_text(undescribed), None);
+    }
+
+    #[test]
+    fn refs_are_resolved_once_and_reused_across_fetches() {
+        let mut server = Server::new();
+        let head = mock_head(&mut server).expect(1);
+        let mut files = Vec::new();
+        for name in ["README.md", "LICENSE"] {
+            files.push(server.mock("GET", format!("/repos/octocat/hello-world/contents/{}", name).as_str())
+                .match_query(Matcher::UrlEncoded("ref".into(), SHA.into()))
+                .with_body(fixture("contents_readme.json"))
+                .expect(1)
+                .create());
+        }
+        let fs = mock_fs(&server, None);
+
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "LICENSE").unwrap(), b"Hello World!\n");
+        assert_eq!(fs.pinned_ref("octocat/hello-world", None).as_deref(), Some(SHA));
+        head.assert();
+        for file in files {
+            file.assert();
+        }
+    }
+
+    #[test]
+    fn hide_binary_detects_by_extension_and_strictly_by_content() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/.gitattributes")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .create();
+        let mut samples = Vec::new();
+        for (name, content) in [("data.dat", &b"\x00\x01binary"[..]), ("notes.txt", &b"plain text\n"[..])] {
+            samples.push(server.mock("GET", format!("/repos/octocat/hello-world/contents/{}", name).as_str())
+                .match_query(Matcher::Any)
+                .with_body(serde_json::json!({"type": "file", "encoding": "base64", "content": BASE64.encode(content)}).to_string())
+                .expect(1)
+                .create());
+        }
+        let mut fs = mock_fs(&server, None);
+        fs.options.hide_binary = true;
+        let repo_id = add_hello_world(&fs);
+        let files = entries(&[("logo.png", "file"), ("data.dat", "file"), ("notes.txt", "file"), ("assets.zip", "dir")]);
+        let hidden = |fs: &GitHubFS| -> Vec<String> {
+            files.iter().filter(|file| fs.is_hidden_binary(Some(repo_id), file)).map(|file| file.name.clone()).collect()
+        };
+
+        assert_eq!(hidden(&fs), ["logo.png"]);
+        fs.options.hide_binary_strict = true;
+        assert_eq!(hidden(&fs), ["logo.png", "data.dat"]);
+        for sample in samples {
+            sample.assert();
+        }
+    }
+
+    #[test]
+    fn live_prefixes_refetch_every_time_while_the_rest_is_cached() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let live = server.mock("GET", "/repos/octocat/hello-world/contents/docs/guide.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .expect(2)
+            .create();
+        let cached = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.live_prefixes = vec!["/hello-world/docs/".to_string()];
+        add_hello_world(&fs);
+
+        for _ in 0..2 {
+            assert_eq!(fs.fetch_file_content("octocat/hello-world", "docs/guide.md").unwrap(), b"Hello World!\n");
+            assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        }
+        assert!(!fs.is_live("octocat/hello-world", "docs-old/guide.md"));
+        live.assert();
+        cached.assert();
+    }
+
+    #[test]
+    fn rate_limited_requests_fail_with_eagain() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_header("X-RateLimit-Remaining", "0")
+            .with_header("X-RateLimit-Reset", "1700000000")
+            .with_body(r#"{"message": "API rate limit exceeded"}"#)
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_header("X-RateLimit-Remaining", "42")
+            .with_body(r#"{"message": "Resource not accessible"}"#)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+
+        let err = fs.fetch_file_content("octocat/hello-world", "README.md").unwrap_err();
+        assert!(is_rate_limited(&err));
+        assert_eq!(errno_for(&err), EAGAIN);
+        assert!(err.to_string().contains("resets at 1700000000"), "{}", err);
+        // A 403 with quota left is a permission problem, not a rate limit
+        let err = fs.load_files(repo_id, "").unwrap_err();
+        assert_eq!(errno_for(&err), EACCES);
+    }
+
+    #[test]
+    fn server_errors_are_retried_until_the_request_succeeds() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let failing = server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .expect(2)
+            .create();
+        let listing = server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .expect(1)
+            .create();
+        let missing = server.mock("GET", "/repos/octocat/hello-world/contents/private")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.max_retries = 3;
+
+        assert_eq!(fs.fetch_directory("octocat/hello-world", "").unwrap().len(), 2);
+        // Client errors are never retried
+        assert_eq!(fs.fetch_directory("octocat/hello-world", "private").unwrap_err().kind(), ErrorKind::NotFound);
+        failing.assert();
+        listing.assert();
+        missing.assert();
+    }
+
+    #[test]
+    fn load_files_caches_the_listing_with_stable_inodes() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let listing = server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), SHA.into()))
+            .with_body(fixture("contents_root.json"))
+            .create();
+
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let files = fs.load_files(repo_id, "").unwrap();
+
+        let names: Vec<&str> = files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["README.md", "src"]);
+        assert_eq!(files[1].kind(), FileType::Directory);
+        assert_eq!(fs.files.read().unwrap().get(&repo_id).map(Vec::len), Some(2));
+
+        let readme = fs.path_inode(repo_id, "README.md");
+        assert_eq!(fs.locate(readme), Some(("octocat/hello-world".to_string(), "README.md".to_string())));
+        assert_eq!(fs.path_inode(repo_id, "/README.md"), readme);
+        listing.assert();
+    }
+
+    #[test]
+    fn prefetch_downloads_only_small_files() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(r#"[
+                {"name": "small.txt", "path": "small.txt", "type": "file", "size": 13, "sha": "1111111111111111111111111111111111111111"},
+                {"name": "big.bin", "path": "big.bin", "type": "file", "size": 1048576, "sha": "2222222222222222222222222222222222222222"}
+            ]"#)
+            .create();
+        let small = server.mock("GET", "/repos/octocat/hello-world/contents/small.txt")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+        let big = server.mock("GET", "/repos/octocat/hello-world/contents/big.bin")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.prefetch = true;
+        fs.options.prefetch_content_max = DEFAULT_PREFETCH_CONTENT_MAX;
+        let repo_id = add_hello_world(&fs);
+
+        fs.load_files(repo_id, "").unwrap();
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "small.txt").unwrap(), b"Hello World!\n");
+        small.assert();
+        big.assert();
+    }
+
+    #[test]
+    fn fetch_file_content_decodes_and_caches() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let content = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::UrlEncoded("ref".into(), SHA.into()))
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+
+        let fs = mock_fs(&server, None);
+
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        content.assert();
+    }
+
+    #[test]
+    fn bench_walks_and_reads_a_mocked_repository() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world")
+            .with_body(r#"{"name": "hello-world", "full_name": "octocat/hello-world"}"#)
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/src")
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .create();
+        let fs = mock_fs(&server, None);
+
+        let report = crate::bench::run(&fs, "octocat/hello-world", 1024).unwrap();
+        assert_eq!((report.first_listing_entries, report.directories, report.files), (2, 2, 1));
+        assert_eq!((report.files_read, report.bytes_read), (1, 13));
+        assert_eq!((report.requests, report.cache_hits, report.cache_misses), (5, 0, 1));
+        assert!(report.to_string().starts_with("Benchmark for octocat/hello-world"));
+    }
+
+    #[test]
+    fn line_endings_convert_crlf_text_and_its_size() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/.gitattributes")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/notes.txt")
+            .match_query(Matcher::Any)
+            .with_body(serde_json::json!({"type": "file", "encoding": "base64", "content": BASE64.encode(fixture("crlf.txt"))}).to_string())
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.line_endings = LineEndings::Lf;
+        let repo_id = add_hello_world(&fs);
+        fs.store_listing(repo_id, repo_id, &entries(&[("notes.txt", "file")]));
+        let ino = fs.path_inode(repo_id, "notes.txt");
+
+        let content = fs.served_content(ino, 0, "octocat/hello-world", "notes.txt").unwrap();
+        assert_eq!(&content[..], b"line one\nline two\n");
+        assert_eq!(fs.attr(ino).unwrap().size, 18);
+        assert_eq!(fs.transform_content(None, b"bin\r\n\0".to_vec()), b"bin\r\n\0");
+    }
+
+    #[test]
+    fn ci_status_reports_the_latest_run_conclusion() {
+        let mut server = Server::new();
+        let runs = server.mock("GET", "/repos/octocat/hello-world/actions/runs")
+            .match_query(Matcher::UrlEncoded("per_page".into(), "1".into()))
+            .with_body(r#"{"total_count": 2, "workflow_runs": [{"status": "completed", "conclusion": "failure"}, {"status": "completed", "conclusion": "success"}]}"#)
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.with_ci = true;
+        let repo_id = add_hello_world(&fs);
+
+        assert_eq!(fs.ci_status(repo_id).as_deref(), Some("failure\n"));
+        assert_eq!(fs.meta_file_content(repo_id, CI_STATUS_NAME).as_deref(), Some(&b"failure\n"[..]));
+        runs.assert();
+
+        let runs: WorkflowRuns = serde_json::from_str(r#"{"workflow_runs": [{"status": "in_progress", "conclusion": null}]}"#).unwrap();
+        assert_eq!(runs.status_line().as_deref(), Some("in_progress\n"));
+        let runs: WorkflowRuns = serde_json::from_str(r#"{"total_count": 0, "workflow_runs": []}"#).unwrap();
+        assert_eq!(runs.status_line(), None);
+    }
+
+    #[test]
+    fn gitattributes_marks_bin_files_as_binary() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let gitattributes = server.mock("GET", "/repos/octocat/hello-world/contents/.gitattributes")
+            .match_query(Matcher::Any)
+            .with_body(serde_json::json!({"type": "file", "encoding": "base64", "content": BASE64.encode("*.bin binary\n*.txt text\n")}).to_string())
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.line_endings = LineEndings::Lf;
+        let repo_id = add_hello_world(&fs);
+
+        assert_eq!(fs.attribute_binary(repo_id, "firmware/image.bin"), Some(true));
+        assert_eq!(fs.attribute_binary(repo_id, "notes.txt"), Some(false));
+        assert_eq!(fs.attribute_binary(repo_id, "main.rs"), None);
+        // Without the attribute, the NUL heuristic would convert this text
+        assert_eq!(fs.transform_content(Some((repo_id, "data.bin")), b"a\r\nb\r\n".to_vec()), b"a\r\nb\r\n");
+        assert_eq!(fs.transform_content(Some((repo_id, "notes.txt")), b"a\r\nb\r\n".to_vec()), b"a\nb\n");
+        gitattributes.assert();
+    }
+
+    #[test]
+    fn decode_base64_ignores_line_breaks() {
+        let wrapped = "SGVsbG8g\nV29y\r\nbGQhCg==\n";
+        assert_eq!(decode_base64(wrapped).unwrap(), b"Hello World!\n");
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn files_just_over_one_megabyte_come_from_the_blobs_api() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let content: Vec<u8> = (0..1024 * 1024 + 1).map(|n| (n % 251) as u8).collect();
+        server.mock("GET", "/repos/octocat/hello-world/contents/data.bin")
+            .match_query(Matcher::Any)
+            .with_body(serde_json::json!({"type": "file", "encoding": "none", "content": "", "size": content.len(), "sha": SHA}).to_string())
+            .create();
+        let blob = server.mock("GET", format!("/repos/octocat/hello-world/git/blobs/{}", SHA).as_str())
+            .with_body(serde_json::json!({"encoding": "base64", "content": BASE64.encode(&content), "size": content.len()}).to_string())
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "data.bin").unwrap(), content);
+        blob.assert();
+    }
+
+    #[test]
+    fn fetch_file_content_falls_back_to_the_blob_without_encoding() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/big.txt")
+            .match_query(Matcher::Any)
+            .with_body(format!(r#"{{"type": "file", "encoding": "", "content": "", "sha": "{}"}}"#, SHA))
+            .create();
+        let blob = server.mock("GET", format!("/repos/octocat/hello-world/git/blobs/{}", SHA).as_str())
+            .with_body(r#"{"encoding": "base64", "content": "SGVsbG8gV29ybGQhCg=="}"#)
+            .create();
+
+        let fs = mock_fs(&server, None);
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "big.txt").unwrap(), b"Hello World!\n");
+        blob.assert();
+    }
+
+    #[test]
+    fn fetch_file_content_downloads_raw_with_none_encoding() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/big.txt")
+            .match_query(Matcher::Any)
+            .match_header("accept", GITHUB_JSON)
+            .with_body(r#"{"type": "file", "encoding": "none", "content": ""}"#)
+            .create();
+        let raw = server.mock("GET", "/repos/octocat/hello-world/contents/big.txt")
+            .match_query(Matcher::Any)
+            .match_header("accept", "application/vnd.github.raw")
+            .with_body("Hello World!\n")
+            .create();
+
+        let fs = mock_fs(&server, None);
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "big.txt").unwrap(), b"Hello World!\n");
+        raw.assert();
+    }
+
+    #[test]
+    fn fetch_file_content_maps_missing_files_to_enoent() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/missing.txt")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create();
+
+        let err = mock_fs(&server, None).fetch_file_content("octocat/hello-world", "missing.txt").unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(errno_for(&err), ENOENT);
+    }
+
+    #[test]
+    fn fetch_file_content_rejects_directory_listings() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/src")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+
+        let err = mock_fs(&server, None).fetch_file_content("octocat/hello-world", "src").unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::IsADirectory);
+        assert_eq!(errno_for(&err), EISDIR);
+    }
+
+    #[test]
+    fn expired_listings_show_pushed_files() {
+        let mut server = Server::new();
+        let head = mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), SHA.into()))
+            .with_body(fixture("contents_root.json"))
+            .create();
+
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        assert_eq!(fs.directory_listing(repo_id).unwrap().map(|files| files.len()), Some(2));
+
+        // A push moves HEAD and adds a file
+        let pushed = "0c6ae2e1c3c5d1a4b1a0f6a5e0ed3d5b5a6a4e21";
+        let mut listing: Vec<serde_json::Value> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+        listing.push(serde_json::json!({"name": "NEWS.md", "path": "NEWS.md", "type": "file", "size": 5, "sha": "1", "download_url": null}));
+        head.remove();
+        server.mock("GET", "/repos/octocat/hello-world/commits/HEAD").with_body(pushed).create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), pushed.into()))
+            .with_body(serde_json::to_string(&listing).unwrap())
+            .create();
+
+        assert_eq!(fs.directory_listing(repo_id).unwrap().map(|files| files.len()), Some(2));
+        fs.expire_listings();
+        let names: Vec<String> = fs.directory_listing(repo_id).unwrap().unwrap().into_iter().map(|file| file.name).collect();
+        assert_eq!(names, ["README.md", "src", "NEWS.md"]);
+    }
+
+    #[test]
+    fn default_branch_changes_reload_the_repository() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let metadata = server.mock("GET", "/repos/octocat/hello-world")
+            .with_body(r#"{"name": "hello-world", "full_name": "octocat/hello-world", "default_branch": "trunk"}"#)
+            .expect(1)
+            .create();
+        let listing = server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), SHA.into()))
+            .with_body(fixture("contents_root.json"))
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        fs.store_listing(repo_id, repo_id, &entries(&[("old.txt", "file")]));
+
+        fs.refresh_default_branches();
+        fs.refresh_default_branches();
+
+        assert_eq!(fs.repos.read().unwrap()[&repo_id].default_branch.as_deref(), Some("trunk"));
+        let names: Vec<String> = fs.files.read().unwrap()[&repo_id].iter().map(|file| file.name.clone()).collect();
+        assert_eq!(names, ["README.md", "src"]);
+        metadata.assert();
+        listing.assert();
+    }
+
+    #[test]
+    fn unavailable_for_legal_reasons_shows_a_dmca_marker() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_status(451)
+            .with_body(r#"{"message":"Repository access blocked"}"#)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+
+        let err = fs.load_files(repo_id, "").unwrap_err();
+        assert!(is_unavailable_for_legal_reasons(&err));
+        assert_eq!(errno_for(&err), EACCES);
+        let entries = fs.directory_entries(repo_id, false).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(_inode, _kind, name)| name.as_str()).collect();
+        assert_eq!(names, [".", "..", DMCA_MARKER_NAME]);
+        let message = fs.marker_message(entries[2].0).unwrap();
+        assert!(message.starts_with("octocat/hello-world is unavailable for legal reasons."));
+    }
+
+    #[test]
+    fn failing_repositories_are_quarantined_until_a_reload() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let listing = server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .expect(2)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.quarantine_after = Some(2);
+        let repo_id = add_hello_world(&fs);
+
+        assert!(fs.load_repo_root(repo_id).is_err());
+        assert!(fs.repo_marker(repo_id).is_none());
+        assert!(fs.load_repo_root(repo_id).is_err());
+        assert_eq!(fs.repo_marker(repo_id).map(|marker| marker.name), Some(ERROR_MARKER_NAME));
+        let err = fs.load_repo_root(repo_id).unwrap_err();
+        assert!(err.to_string().contains("quarantined"));
+        listing.assert();
+
+        fs.lift_quarantines();
+        assert!(!fs.is_quarantined(repo_id));
+        assert!(fs.repo_marker(repo_id).is_none());
+    }
+
+    #[test]
+    fn negative_lookups_expire_and_reset_with_new_listings() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.negative_ttl = Some(Duration::from_millis(50));
+        let repo_id = add_hello_world(&fs);
+        let name = OsStr::new(".git");
+
+        fs.remember_missing(repo_id, name);
+        assert!(fs.is_known_missing(repo_id, name));
+        assert!(!fs.is_known_missing(repo_id, OsStr::new(".hidden")));
+        thread::sleep(Duration::from_millis(60));
+        assert!(!fs.is_known_missing(repo_id, name));
+
+        fs.remember_missing(repo_id, name);
+        fs.store_listing(repo_id, repo_id, &[]);
+        assert!(!fs.is_known_missing(repo_id, name));
+    }
+
+    #[test]
+    fn negative_lookups_are_off_without_ttl() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+
+        fs.remember_missing(1, OsStr::new(".git"));
+        assert!(!fs.is_known_missing(1, OsStr::new(".git")));
+    }
+
+    fn repos_named(names: &[&str]) -> Vec<GitHubRepository> {
+        names.iter()
+            .map(|name| serde_json::from_value(serde_json::json!({"name": name, "full_name": format!("octocat/{}", name)})).unwrap())
+            .collect()
+    }
+
+    fn filtered(include: &[&str], exclude: &[&str]) -> Vec<String> {
+        let include: Vec<String> = include.iter().map(|pattern| pattern.to_string()).collect();
+        let exclude: Vec<String> = exclude.iter().map(|pattern| pattern.to_string()).collect();
+        filter_by_patterns(repos_named(&["api", "api-docs", "web", "Web-legacy"]), &include, &exclude)
+            .into_iter()
+            .map(|repo| repo.name)
+            .collect()
+    }
+
+    #[test]
+    fn warming_lists_each_repository_root_up_to_the_limit() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex(r"^/repos/octocat/[a-z]+/commits/HEAD$".into()))
+            .with_body(SHA)
+            .create();
+        let listings: Vec<_> = ["api", "web", "docs"].iter()
+            .map(|name| {
+                server.mock("GET", format!("/repos/octocat/{}/contents/", name).as_str())
+                    .match_query(Matcher::Any)
+                    .with_body("[]")
+                    .expect(if *name == "docs" { 0 } else { 1 })
+                    .create()
+            })
+            .collect();
+        let mut fs = mock_fs(&server, None);
+        fs.options.warm_limit = Some(2);
+        let repo_inodes: Vec<u64> = repos_named(&["api", "web", "docs"]).into_iter()
+            .map(|repo| fs.add_repository(repo))
+            .collect();
+
+        assert_eq!(fs.warm_repo_roots(&repo_inodes).unwrap(), 2);
+        for listing in listings {
+            listing.assert();
+        }
+    }
+
+    #[test]
+    fn too_large_directories_fall_back_to_the_trees_api() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/big")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"message": "This directory is too large to render"}"#)
+            .create();
+        let tree = server.mock("GET", format!("/repos/octocat/hello-world/git/trees/{}:big", SHA).as_str())
+            .with_body(r#"{"tree": [
+                {"path": "a.txt", "mode": "100644", "type": "blob", "sha": "1", "size": 3},
+                {"path": "nested", "mode": "040000", "type": "tree", "sha": "2"},
+                {"path": "link", "mode": "120000", "type": "blob", "sha": "3", "size": 5}
+            ], "truncated": false}"#)
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+
+        let files = fs.fetch_directory("octocat/hello-world", "big").unwrap();
+        let listing: Vec<(&str, &str, u64)> = files.iter()
+            .map(|file| (file.path.as_str(), file.file_type.as_str(), file.size))
+            .collect();
+        assert_eq!(listing, [("big/a.txt", "file", 3), ("big/nested", "dir", 0), ("big/link", "symlink", 5)]);
+        tree.assert();
+    }
+
+    #[test]
+    fn keepalive_detects_a_revoked_token() {
+        let mut server = Server::new();
+        let client = Client::new();
+        server.mock("GET", "/user")
+            .match_header("Authorization", "Bearer revoked")
+            .with_status(401)
+            .with_body(r#"{"message": "Bad credentials"}"#)
+            .create();
+        server.mock("GET", "/user")
+            .match_header("Authorization", "Bearer valid")
+            .with_header("X-OAuth-Scopes", "repo, read:org")
+            .with_body(fixture("user.json"))
+            .create();
+
+        assert_eq!(check_token(&client, &server.url(), None, "revoked"), TokenCheck::Revoked);
+        assert_eq!(check_token(&client, &server.url(), None, "valid"), TokenCheck::Valid(Some("repo, read:org".to_string())));
+    }
+
+    #[test]
+    fn sizes_file_lists_human_readable_sizes() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.with_sizes = true;
+        let repo_id = add_hello_world(&fs);
+        let files: Vec<GitHubFile> = serde_json::from_value(serde_json::json!([
+            {"name": "tiny.txt", "path": "tiny.txt", "type": "file", "size": 512},
+            {"name": "docs", "path": "docs", "type": "dir", "size": 0},
+            {"name": "image.png", "path": "image.png", "type": "file", "size": 1536},
+            {"name": "video.mp4", "path": "video.mp4", "type": "file", "size": 5 * 1024 * 1024 + 300 * 1024},
+        ])).unwrap();
+        fs.store_listing(repo_id, repo_id, &files);
+
+        let expected = "     512 B  tiny.txt\n         -  docs\n    1.5 KB  image.png\n    5.3 MB  video.mp4\n";
+        let ino = fs.synthetic_inode(repo_id, SIZES_NAME);
+        assert_eq!(String::from_utf8(fs.synthetic_content(ino).unwrap()).unwrap(), expected);
+        assert_eq!(fs.attr(ino).unwrap().size, expected.len() as u64);
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn union_branches_merge_listings_and_record_origins() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex(r"^/repos/octocat/hello-world/commits/".into()))
+            .with_status(404)
+            .create();
+        let listing = |files: &[(&str, u64)]| {
+            let files: Vec<_> = files.iter()
+                .map(|(name, size)| serde_json::json!({"name": name, "path": name, "type": "file", "size": size}))
+                .collect();
+            serde_json::Value::from(files).to_string()
+        };
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), "main".into()))
+            .with_body(listing(&[("README.md", 13), ("main.rs", 1)]))
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::UrlEncoded("ref".into(), "dev".into()))
+            .with_body(listing(&[("README.md", 99), ("dev.rs", 2)]))
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.union_branches = vec!["main".to_string(), "dev".to_string()];
+        let repo_id = add_hello_world(&fs);
+
+        fs.load_repo_root(repo_id).unwrap();
+        let files = fs.files.read().unwrap().get(&repo_id).cloned().unwrap();
+        let merged: Vec<(&str, u64)> = files.iter().map(|file| (file.name.as_str(), file.size)).collect();
+        assert_eq!(merged, [("README.md", 13), ("main.rs", 1), ("dev.rs", 2)]);
+        let branch = |name: &str| {
+            fs.xattrs(fs.path_inode(repo_id, name)).into_iter()
+                .find(|(xattr, _value)| *xattr == XATTR_BRANCH)
+                .map(|(_xattr, value)| value)
+        };
+        assert_eq!(branch("README.md").as_deref(), Some("main"));
+        assert_eq!(branch("main.rs").as_deref(), Some("main"));
+        assert_eq!(branch("dev.rs").as_deref(), Some("dev"));
+    }
+
+    #[test]
+    fn license_filter_keeps_only_matching_repositories() {
+        let mut server = Server::new();
+        let repo = |name: &str, license: serde_json::Value| {
+            serde_json::json!({"name": name, "full_name": format!("octocat/{}", name), "license": license})
+        };
+        let listing = serde_json::json!([
+            repo("mit-lib", serde_json::json!({"key": "mit", "name": "MIT License", "spdx_id": "MIT"})),
+            repo("apache-lib", serde_json::json!({"key": "apache-2.0", "name": "Apache License 2.0", "spdx_id": "Apache-2.0"})),
+            repo("unlicensed", serde_json::Value::Null),
+        ]);
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(listing.to_string())
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.licenses = vec!["mit".to_string()];
+        fs.options.with_license = true;
+
+        let repos = fs.fetch_repositories().unwrap();
+        let names: Vec<&str> = repos.iter().map(|repo| repo.name.as_str()).collect();
+        assert_eq!(names, ["mit-lib"]);
+        let repo_id = fs.add_repository(repos.into_iter().next().unwrap());
+        assert_eq!(fs.meta_file_content(repo_id, LICENSE_NAME).unwrap(), b"MIT\n");
+    }
+
+    #[test]
+    fn startup_progress_advances_once_per_repository() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex(r"^/repos/octocat/[a-z]+/commits/HEAD$".into()))
+            .with_body(SHA)
+            .create();
+        server.mock("GET", Matcher::Regex(r"^/repos/octocat/(api|web)/contents/$".into()))
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        server.mock("GET", "/repos/octocat/broken/contents/")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_inodes: Vec<u64> = repos_named(&["api", "broken", "web"]).into_iter()
+            .map(|repo| fs.add_repository(repo))
+            .collect();
+
+        assert_eq!(fs.warm_repo_roots(&repo_inodes).unwrap(), 3);
+    }
+
+    #[test]
+    fn startup_lists_repositories_without_loading_their_roots() {
+        let mut server = Server::new();
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(fixture("user_repos.json"))
+            .create();
+        let roots = server.mock("GET", Matcher::Regex(r"^/repos/".into()))
+            .expect(0)
+            .create();
+        let options = FsOptions {
+            api_url: Some(server.url()),
+            cache_size: DEFAULT_CACHE_SIZE_MB * 1024 * 1024,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            ..FsOptions::default()
+        };
+
+        let fs = GitHubFS::new("octocat".to_string(), None, options).unwrap();
+        assert!(!fs.repos.read().unwrap().is_empty());
+        roots.assert();
+    }
+
+    #[test]
+    fn pages_without_link_headers_are_fetched_by_number() {
+        let mut server = Server::new();
+        let page = |first: usize, count: usize| {
+            let repos: Vec<_> = (first..first + count)
+                .map(|n| serde_json::json!({"name": format!("repo-{}", n), "full_name": format!("octocat/repo-{}", n)}))
+                .collect();
+            serde_json::Value::from(repos).to_string()
+        };
+        let pages = [
+            (format!("per_page={}", PER_PAGE), page(0, PER_PAGE), 1),
+            (format!("per_page={}&page=2", PER_PAGE), page(PER_PAGE, PER_PAGE), 1),
+            (format!("per_page={}&page=3", PER_PAGE), page(2 * PER_PAGE, 5), 1),
+            (format!("per_page={}&page=4", PER_PAGE), page(0, 0), 0),
+        ];
+        let mocks: Vec<_> = pages.into_iter()
+            .map(|(query, body, hits)| {
+                server.mock("GET", "/users/octocat/repos")
+                    .match_query(Matcher::Exact(query))
+                    .with_body(body)
+                    .expect(hits)
+                    .create()
+            })
+            .collect();
+        let fs = mock_fs(&server, None);
+
+        let repos = fs.fetch_repositories().unwrap();
+        assert_eq!(repos.len(), 2 * PER_PAGE + 5);
+        assert_eq!(repos.last().unwrap().name, format!("repo-{}", 2 * PER_PAGE + 4));
+        for mock in mocks {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn paginated_repositories_all_appear_under_the_root() {
+        let mut server = Server::new();
+        let page = |first: usize, count: usize| {
+            let repos: Vec<_> = (first..first + count)
+                .map(|n| serde_json::json!({"name": format!("repo-{}", n), "full_name": format!("octocat/repo-{}", n)}))
+                .collect();
+            serde_json::Value::from(repos).to_string()
+        };
+        let next = format!("{}/users/octocat/repos?per_page={}&page=2", server.url(), PER_PAGE);
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Exact(format!("per_page={}", PER_PAGE)))
+            .with_header("Link", &format!("<{}>; rel=\"next\"", next))
+            .with_body(page(0, PER_PAGE))
+            .create();
+        server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Exact(format!("per_page={}&page=2", PER_PAGE)))
+            .with_body(page(PER_PAGE, 3))
+            .create();
+        let fs = mock_fs(&server, None);
+
+        for repo in fs.fetch_repositories().unwrap() {
+            fs.add_repository(repo);
+        }
+        let names: Vec<String> = fs.directory_entries(1, true).unwrap().into_iter().map(|(_ino, _kind, name)| name).collect();
+        assert_eq!(names.iter().filter(|name| name.starts_with("repo-")).count(), PER_PAGE + 3);
+        assert!(names.contains(&"repo-0".to_string()) && names.contains(&format!("repo-{}", PER_PAGE + 2)));
+    }
+
+    #[test]
+    fn enterprise_api_urls_are_built_with_or_without_a_trailing_slash() {
+        for base in ["https://ghe.corp/api/v3", "https://ghe.corp/api/v3/"] {
+            let options = FsOptions { api_url: Some(base.to_string()), ..FsOptions::default() };
+            let fs = GitHubFS::unloaded("octocat".to_string(), None, options).unwrap();
+            assert_eq!(fs.api_url, "https://ghe.corp/api/v3");
+            assert_eq!(fs.contents_url("octocat/hello-world", "src/main.rs"), "https://ghe.corp/api/v3/repos/octocat/hello-world/contents/src/main.rs");
+            assert_eq!(fs.web_url(), "https://ghe.corp");
+        }
+
+        let mut server = Server::new();
+        let repos = server.mock("GET", "/users/octocat/repos")
+            .match_query(Matcher::Any)
+            .with_body(fixture("user_repos.json"))
+            .create();
+        let options = FsOptions {
+            api_url: Some(format!("{}/", server.url())),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            ..FsOptions::default()
+        };
+        let fs = GitHubFS::unloaded("octocat".to_string(), None, options).unwrap();
+        assert_eq!(fs.fetch_repositories().unwrap().len(), 2);
+        repos.assert();
+    }
+
+    #[test]
+    fn repository_globs_match_name_or_full_name() {
+        assert_eq!(filtered(&[], &[]), ["api", "api-docs", "web", "Web-legacy"]);
+        assert_eq!(filtered(&["api*"], &[]), ["api", "api-docs"]);
+        assert_eq!(filtered(&["octocat/web*"], &[]), ["web", "Web-legacy"]);
+        assert_eq!(filtered(&["other/*"], &[]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn repository_excludes_win_over_includes() {
+        assert_eq!(filtered(&["api*", "web"], &["*-docs"]), ["api", "web"]);
+        assert_eq!(filtered(&["web*"], &["web*"]), Vec::<String>::new());
+        assert_eq!(filtered(&[], &["*legacy", "api"]), ["api-docs", "web"]);
+    }
+
+    #[test]
+    fn name_collisions_follow_the_strategy() {
+        let repos: Vec<GitHubRepository> = serde_json::from_value(serde_json::json!([
+            {"name": "utils", "full_name": "alice/utils"},
+            {"name": "utils", "full_name": "acme/utils"},
+            {"name": "utils", "full_name": "acme/utils"},
+        ])).unwrap();
+        let names = |strategy| -> Vec<String> {
+            resolve_name_collisions(repos.clone(), strategy).unwrap().into_iter().map(|repo| repo.name).collect()
+        };
+
+        assert_eq!(names(CollisionStrategy::Suffix), ["utils", "utils-acme", "utils-acme-2"]);
+        assert_eq!(names(CollisionStrategy::Skip), ["utils"]);
+        let err = resolve_name_collisions(repos.clone(), CollisionStrategy::Error).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn repository_flags_filter_only_when_asked() {
+        let repos: Vec<GitHubRepository> = serde_json::from_value(serde_json::json!([
+            {"name": "active", "full_name": "octocat/active", "private": true},
+            {"name": "old", "full_name": "octocat/old", "archived": true, "private": true},
+            {"name": "fork", "full_name": "octocat/fork", "fork": true},
+            {"name": "site", "full_name": "octocat/site"},
+        ])).unwrap();
+        let names = |options: FsOptions| -> Vec<String> {
+            filter_by_flags(repos.clone(), &options).into_iter().map(|repo| repo.name).collect()
+        };
+
+        assert_eq!(names(FsOptions::default()), ["active", "old", "fork", "site"]);
+        assert_eq!(names(FsOptions { no_archived: true, ..FsOptions::default() }), ["active", "fork", "site"]);
+        assert_eq!(names(FsOptions { no_forks: true, ..FsOptions::default() }), ["active", "old", "site"]);
+        assert_eq!(names(FsOptions { only_private: true, no_archived: true, ..FsOptions::default() }), ["active"]);
+    }
+
+    #[test]
+    fn repository_description_is_an_xattr_of_the_root() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repos: Vec<GitHubRepository> = serde_json::from_str(&fixture("user_repos.json")).unwrap();
+        let inodes: Vec<u64> = repos.into_iter().map(|repo| fs.add_repository(repo)).collect();
+
+        assert_eq!(fs.xattrs(inodes[0]), [(XATTR_DESCRIPTION, "My first repository".to_string())]);
+        assert!(fs.xattrs(inodes[1]).is_empty());
+    }
+
+    #[test]
+    fn codeowners_xattr_matches_paths_against_the_root_codeowners() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/.github/CODEOWNERS")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create();
+        let codeowners = server.mock("GET", "/repos/octocat/hello-world/contents/CODEOWNERS")
+            .match_query(Matcher::Any)
+            .with_body(serde_json::json!({"type": "file", "encoding": "base64", "content": BASE64.encode(fixture("CODEOWNERS"))}).to_string())
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.with_codeowners = true;
+        let repo_id = add_hello_world(&fs);
+        let owners = |path: &str| fs.codeowners_xattr(fs.path_inode(repo_id, path));
+
+        assert_eq!(owners("README.md").as_deref(), Some("@octocat"));
+        assert_eq!(owners("docs/guide.md").as_deref(), Some("@octocat/docs-team"));
+        assert_eq!(owners("src/main.rs").as_deref(), Some("@rustaceans @octocat"));
+        assert_eq!(owners("vendor/lib.c"), None);
+        codeowners.assert();
+    }
+
+    #[test]
+    fn persisted_etags_are_revalidated_on_the_next_mount() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let first = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_header("ETag", "\"readme-v1\"")
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+        let dir = std::env::temp_dir().join(format!("githubfs-test-{}", std::process::id()));
+        let persisted = |server: &Server| {
+            let mut fs = mock_fs(server, None);
+            fs.options.persist_dir = Some(dir.clone());
+            fs.load_persisted_cache();
+            fs
+        };
+
+        let fs = persisted(&server);
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        fs.persist_cache();
+        first.assert();
+
+        let revalidated = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .match_header("if-none-match", "\"readme-v1\"")
+            .with_status(304)
+            .expect(1)
+            .create();
+        let fs = persisted(&server);
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        assert_eq!(fs.metrics.not_modified.load(Ordering::Relaxed), 1);
+        revalidated.assert();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_shares_split_the_whole_budget() {
+        assert_eq!(cache_shares(1000), (500, 250, 250));
+        assert_eq!(cache_shares(7), (3, 1, 3));
+        assert_eq!(cache_shares(0), (0, 0, 0));
+    }
+
+    #[test]
+    fn saved_etag_responses_are_evicted_past_the_cache_size() {
+        let mut server = Server::new();
+        server.mock("GET", Matcher::Regex("^/repos/octocat/hello-world/contents/".to_string()))
+            .with_header("ETag", "\"v1\"")
+            .with_body([b'x'; 60])
+            .create();
+        let fs = mock_fs(&server, None);
+        *fs.etags.lock().unwrap() = ContentCache::new(100);
+        let url = |path: &str| format!("{}/repos/octocat/hello-world/contents/{}", server.url(), path);
+
+        fs.get_bytes(&url("first"), GITHUB_JSON).unwrap();
+        fs.get_bytes(&url("second"), GITHUB_JSON).unwrap();
+
+        let etags = fs.etags.lock().unwrap();
+        assert_eq!((etags.len(), etags.size()), (1, 60));
+        assert!(etags.contains_key(&format!("{} {}", GITHUB_JSON, url("second"))));
+    }
+
+    #[test]
+    fn nlink_counts_subdirectories() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        assert_eq!(fs.attr(repo_id).unwrap().nlink, 2);
+        fs.load_files(repo_id, "").unwrap();
+
+        assert_eq!(fs.attr(1).unwrap().nlink, 3);
+        assert_eq!(fs.attr(repo_id).unwrap().nlink, 3);
+        assert_eq!(fs.attr(fs.path_inode(repo_id, "README.md")).unwrap().nlink, 1);
+        assert_eq!(fs.attr(fs.path_inode(repo_id, "src")).unwrap().nlink, 2);
+    }
+
+    #[test]
+    fn attr_reports_the_configured_owner() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.uid = 1000;
+        fs.options.gid = 100;
+
+        let attr = fs.attr(1).unwrap();
+        assert_eq!((attr.uid, attr.gid), (1000, 100));
+    }
+
+    #[test]
+    fn attr_reports_the_listed_file_size() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        fs.load_files(repo_id, "").unwrap();
+
+        let readme = fs.attr(fs.path_inode(repo_id, "README.md")).unwrap();
+        assert_eq!((readme.kind, readme.size, readme.blocks), (FileType::RegularFile, 13, 1));
+        let src = fs.attr(fs.path_inode(repo_id, "src")).unwrap();
+        assert_eq!((src.kind, src.size), (FileType::Directory, 0));
+    }
+
+    /// Stores a listing with one large file whose download URL is served
+    /// by `server`, returning the file's inode.
+    fn add_large_file(fs: &GitHubFS, server: &Server) -> u64 {
+        let repo_id = add_hello_world(fs);
+        let listing = format!(
+            r#"[{{"name":"big.bin","path":"big.bin","sha":"{}","size":{},"type":"file","download_url":"{}/raw/big.bin"}}]"#,
+            SHA, RANGE_READ_MIN_SIZE, server.url(),
+        );
+        let files: Vec<GitHubFile> = serde_json::from_str(&listing).unwrap();
+        fs.store_listing(repo_id, repo_id, &files);
+        fs.path_inode(repo_id, "big.bin")
+    }
+
+    #[test]
+    fn each_request_gets_the_next_id() {
+        let mut server = Server::new();
+        server.mock("GET", "/user")
+            .with_header("X-RateLimit-Remaining", "4999")
+            .with_body(fixture("user.json"))
+            .expect(2)
+            .create();
+        let fs = mock_fs(&server, None);
+        let url = format!("{}/user", server.url());
+
+        let response = fs.send_request(&url).unwrap();
+        assert_eq!(rate_limit_remaining(&response), Some(4999));
+        fs.send_request(&url).unwrap();
+        assert_eq!(fs.metrics.requests.load(Ordering::Relaxed), 2);
+        assert_eq!(fs.rate_limit_remaining.load(Ordering::Relaxed), 4999);
+    }
+
+    #[test]
+    fn renamed_repositories_are_followed_and_remembered() {
+        let mut server = Server::new();
+        let moved = server.mock("GET", "/repos/octocat/hello-world")
+            .with_status(301)
+            .with_header("Location", &format!("{}/repos/octocat/hello-universe", server.url()))
+            .expect(1)
+            .create();
+        let renamed = server.mock("GET", "/repos/octocat/hello-universe")
+            .with_body(r#"{"name": "hello-universe", "full_name": "octocat/hello-universe"}"#)
+            .expect(2)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+
+        assert_eq!(fs.fetch_repository("octocat/hello-world").unwrap().full_name, "octocat/hello-universe");
+        assert_eq!(fs.sync_repo_name(repo_id).unwrap(), "octocat/hello-universe");
+        assert_eq!(fs.repos.read().unwrap()[&repo_id].full_name, "octocat/hello-universe");
+        fs.fetch_repository("octocat/hello-world").unwrap();
+        moved.assert();
+        renamed.assert();
+    }
+
+    #[test]
+    fn redirects_to_another_host_drop_the_token() {
+        let mut server = Server::new();
+        let mut storage = Server::new();
+        let redirect = server.mock("GET", "/repos/octocat/hello-world/tarball")
+            .match_header("authorization", "Bearer secret")
+            .with_status(302)
+            .with_header("Location", &format!("{}/archive.tar.gz", storage.url()))
+            .create();
+        let download = storage.mock("GET", "/archive.tar.gz")
+            .match_header("authorization", Matcher::Missing)
+            .with_body("archive")
+            .create();
+        let fs = mock_fs(&server, Some("secret"));
+
+        let response = fs.send_request(&format!("{}/repos/octocat/hello-world/tarball", server.url())).unwrap();
+        assert_eq!(response.text().unwrap(), "archive");
+        redirect.assert();
+        download.assert();
+    }
+
+    #[test]
+    fn reads_in_offset_steps_reconstruct_the_file_from_one_download() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let readme = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        fs.store_listing(repo_id, repo_id, &entries(&[("README.md", "file")]));
+        let ino = fs.path_inode(repo_id, "README.md");
+
+        let mut read = Vec::new();
+        for offset in (0..20).step_by(4) {
+            let content = fs.served_content(ino, offset, "octocat/hello-world", "README.md").unwrap();
+            read.extend_from_slice(read_chunk(&content, offset, 4));
+        }
+        assert_eq!(read, b"Hello World!\n");
+        readme.assert();
+    }
+
+    #[test]
+    fn read_finds_the_file_by_inode_in_its_repository() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+        server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let files = fs.load_files(repo_id, "").unwrap();
+        let ino = fs.find_child(repo_id, &files, OsStr::new("README.md")).unwrap();
+
+        let (full_name, path) = fs.locate(ino).unwrap();
+        assert_eq!((full_name.as_str(), path.as_str()), ("octocat/hello-world", "README.md"));
+        let content = fs.served_content(ino, 0, &full_name, &path).unwrap();
+        assert_eq!(&content[..], b"Hello World!\n");
+    }
+
+    #[test]
+    fn lookup_descends_two_levels_of_subdirectories() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        for (dir, entry, kind) in [("", "src", "dir"), ("src", "src/lib", "dir"), ("src/lib", "src/lib/mod.rs", "file")] {
+            let name = entry.rsplit('/').next().unwrap();
+            server.mock("GET", format!("/repos/octocat/hello-world/contents/{}", dir).as_str())
+                .match_query(Matcher::Any)
+                .with_body(serde_json::json!([{"name": name, "path": entry, "type": kind, "size": 7}]).to_string())
+                .expect(1)
+                .create();
+        }
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+
+        let mut ino = repo_id;
+        for name in ["src", "lib", "mod.rs"] {
+            let files = fs.directory_listing(ino).unwrap().unwrap();
+            ino = fs.find_child(ino, &files, OsStr::new(name)).unwrap();
+        }
+        assert_eq!(fs.resolve(ino), Some((repo_id, "src/lib/mod.rs".to_string())));
+        assert_eq!(fs.attr(ino).unwrap().size, 7);
+        assert!(fs.directory_listing(ino).unwrap().is_none());
+    }
+
+    #[test]
+    fn opening_for_writing_fails_with_erofs() {
+        assert_eq!(check_open_flags(libc::O_RDONLY), Ok(()));
+        assert_eq!(check_open_flags(libc::O_RDONLY | libc::O_NONBLOCK), Ok(()));
+        assert_eq!(check_open_flags(libc::O_WRONLY), Err(EROFS));
+        assert_eq!(check_open_flags(libc::O_RDWR), Err(EROFS));
+        assert_eq!(check_open_flags(libc::O_RDONLY | libc::O_CREAT), Err(EROFS));
+        assert_eq!(check_open_flags(libc::O_RDONLY | libc::O_TRUNC), Err(EROFS));
+    }
+
+    #[test]
+    fn read_chunk_stops_at_the_end_of_the_content() {
+        let content = b"Hello World!\n";
+        let len = content.len() as i64;
+
+        assert_eq!(read_chunk(content, 0, 5), b"Hello");
+        assert_eq!(read_chunk(content, len, 4096), b"");
+        assert_eq!(read_chunk(content, len + 4096, 4096), b"");
+        assert_eq!(read_chunk(content, i64::MAX, u32::MAX), b"");
+        assert_eq!(read_chunk(content, 6, 4096), b"World!\n");
+    }
+
+    #[test]
+    fn read_prefix_requests_a_bounded_range_without_the_token() {
+        let server = Server::new();
+        let mut downloads = Server::new();
+        let prefix = downloads.mock("GET", "/raw/big.bin")
+            .match_header("range", "bytes=0-4095")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(206)
+            .with_body(vec![b'x'; 4096])
+            .expect(1)
+            .create();
+        let mut fs = mock_fs(&server, Some("secret"));
+        fs.options.open_in_pager = true;
+        let ino = add_large_file(&fs, &downloads);
+        let file = fs.file_entry(ino).unwrap();
+
+        assert_eq!(fs.read_prefix(ino, &file, 0, 4096).unwrap().len(), 4096);
+        // LFS pointers must be resolved before serving, so no prefix reads
+        fs.options.resolve_lfs = true;
+        assert_eq!(fs.read_prefix(ino, &file, 4096, 4096), None);
+        prefix.assert();
+    }
+
+    #[test]
+    fn read_range_past_the_end_returns_nothing_without_fetching() {
+        let mut server = Server::new();
+        let range = server.mock("GET", "/raw/big.bin").expect(0).create();
+        let fs = mock_fs(&server, None);
+        let ino = add_large_file(&fs, &server);
+        let file = fs.file_entry(ino).unwrap();
+
+        assert_eq!(fs.read_range(ino, &file, RANGE_READ_MIN_SIZE, 4096).unwrap(), b"");
+        assert_eq!(fs.read_range(ino, &file, u64::MAX, 4096).unwrap(), b"");
+        range.assert();
+    }
+
+    #[test]
+    fn read_range_fetches_only_the_requested_bytes() {
+        let mut server = Server::new();
+        let range = server.mock("GET", "/raw/big.bin")
+            .match_header("range", "bytes=1024-1028")
+            .with_status(206)
+            .with_body("slice")
+            .create();
+        let fs = mock_fs(&server, None);
+        let ino = add_large_file(&fs, &server);
+        let file = fs.file_entry(ino).unwrap();
+
+        assert_eq!(fs.read_range(ino, &file, 1024, 5).unwrap(), b"slice");
+        range.assert();
+    }
+
+    #[test]
+    fn read_range_does_not_send_the_token_to_the_download_host() {
+        let server = Server::new();
+        let mut downloads = Server::new();
+        let range = downloads.mock("GET", "/raw/big.bin")
+            .match_header("range", "bytes=0-4")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(206)
+            .with_body("slice")
+            .create();
+        let fs = mock_fs(&server, Some("secret"));
+        let ino = add_large_file(&fs, &downloads);
+        let file = fs.file_entry(ino).unwrap();
+
+        assert_eq!(fs.read_range(ino, &file, 0, 5).unwrap(), b"slice");
+        range.assert();
+    }
+
+    #[test]
+    fn read_range_slices_the_whole_file_when_range_is_ignored() {
+        let mut server = Server::new();
+        let full = server.mock("GET", "/raw/big.bin")
+            .with_body("0123456789")
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+        let ino = add_large_file(&fs, &server);
+        let file = fs.file_entry(ino).unwrap();
+
+        assert_eq!(fs.read_range(ino, &file, 2, 3).unwrap(), b"234");
+        // The whole file is cached; the next reads don't use Range
+        assert_eq!(fs.read_range(ino, &file, 5, 3), None);
+        full.assert();
+    }
+
+    #[test]
+    fn readme_only_lists_just_the_readme() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        let readme = server.mock("GET", "/repos/octocat/hello-world/readme")
+            .with_body(fixture("contents_readme.json"))
+            .create();
+        let content = server.mock("GET", "/repos/octocat/hello-world/contents/README.md")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_readme.json"))
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.readme_only = true;
+        let repo_id = add_hello_world(&fs);
+
+        let files = fs.load_files(repo_id, "").unwrap();
+        let names: Vec<&str> = files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["README.md"]);
+        assert_eq!(fs.fetch_file_content("octocat/hello-world", "README.md").unwrap(), b"Hello World!\n");
+        readme.assert();
+        content.assert();
+    }
+
+    #[test]
+    fn readme_only_shows_repositories_without_readme_as_empty() {
+        let mut server = Server::new();
+        server.mock("GET", "/repos/octocat/hello-world/readme")
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create();
+        let mut fs = mock_fs(&server, None);
+        fs.options.readme_only = true;
+        let repo_id = add_hello_world(&fs);
+
+        assert!(fs.load_files(repo_id, "").unwrap().is_empty());
+        assert_eq!(fs.directory_entries(repo_id, false).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn readdir_resumes_from_the_offset_without_dropping_entries() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let names: Vec<String> = (0..500).map(|n| format!("file-{:03}", n)).collect();
+        let listing: Vec<(&str, &str)> = names.iter().map(|name| (name.as_str(), "file")).collect();
+        fs.store_listing(repo_id, repo_id, &entries(&listing));
+        let all = fs.directory_entries(repo_id, true).unwrap();
+
+        // Each call fits 64 entries, like a small kernel buffer
+        let mut listed = Vec::new();
+        let mut offset = 0;
+        loop {
+            let mut added = 0;
+            fill_directory(&all, offset, |_inode, next, _kind, name| {
+                if added == 64 {
+                    return true;
+                }
+                added += 1;
+                offset = next;
+                listed.push(name.to_string());
+                false
+            });
+            if added == 0 {
+                break;
+            }
+        }
+        assert_eq!(listed.len(), names.len() + 2);
+        assert_eq!(listed[..2], [".", ".."]);
+        assert_eq!(listed[2..], names[..]);
+    }
+
+    #[test]
+    fn listing_a_directory_twice_leaves_the_maps_unchanged() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .expect(1)
+            .create();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let sizes = |fs: &GitHubFS| (fs.files.read().unwrap().len(), fs.paths.read().unwrap().len(), fs.inodes.read().unwrap().len());
+
+        let first = fs.directory_entries(repo_id, true).unwrap();
+        let after_first = sizes(&fs);
+        assert_eq!(fs.directory_entries(repo_id, true).unwrap(), first);
+        assert_eq!(sizes(&fs), after_first);
+    }
+
+    #[test]
+    fn directory_entries_list_dot_entries_first() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let files: Vec<GitHubFile> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+        fs.store_listing(repo_id, repo_id, &files);
+
+        let names: Vec<String> = fs.directory_entries(repo_id, false).unwrap()
+            .into_iter()
+            .map(|(_inode, _kind, name)| name)
+            .collect();
+        assert_eq!(names, [".", "..", "README.md", "src"]);
+    }
+
+    /// Builds a listing from `(path, type)` pairs.
+    fn entries(entries: &[(&str, &str)]) -> Vec<GitHubFile> {
+        entries.iter()
+            .map(|(path, file_type)| {
+                let name = path.rsplit('/').next().unwrap();
+                serde_json::from_value(serde_json::json!({"name": name, "path": path, "type": file_type, "size": 1})).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deep_directories_collapse_until_a_child_is_looked_up() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.max_depth_display = Some(2);
+        let repo_id = add_hello_world(&fs);
+        let names = |ino: u64| -> Vec<String> {
+            fs.directory_entries(ino, false).unwrap().into_iter().map(|(_inode, _kind, name)| name).collect()
+        };
+        fs.store_listing(repo_id, repo_id, &entries(&[("a", "dir")]));
+        let a = fs.path_inode(repo_id, "a");
+        fs.store_listing(repo_id, a, &entries(&[("a/b", "dir")]));
+        let b = fs.path_inode(repo_id, "a/b");
+        let deep = entries(&[("a/b/c.txt", "file"), ("a/b/d.txt", "file")]);
+        fs.store_listing(repo_id, b, &deep);
+        fs.store_listing(repo_id, fs.path_inode(repo_id, "x"), &entries(&[("x/y/z", "dir")]));
+
+        assert_eq!(names(a), [".", "..", "b"]);
+        assert_eq!(names(b), [".", "..", DEEP_PLACEHOLDER_NAME]);
+        let placeholder = fs.directory_entries(b, false).unwrap()[2].0;
+        assert_ne!(placeholder, fs.directory_entries(fs.path_inode(repo_id, "x"), false).unwrap()[2].0);
+        assert_eq!(fs.synthetic_content(placeholder).unwrap(), b"c.txt\nd.txt\n");
+        assert_eq!(fs.attr(placeholder).unwrap().size, 12);
+
+        let c = fs.find_child(b, &deep, OsStr::new("c.txt")).unwrap();
+        assert_eq!(c, fs.path_inode(repo_id, "a/b/c.txt"));
+        assert_eq!(names(b), [".", "..", "c.txt", "d.txt"]);
+        fs.find_child(a, &entries(&[("a/b", "dir")]), OsStr::new("b")).unwrap();
+        assert_eq!(fs.expanded.read().unwrap().len(), 1);
+        fs.forget_listings(repo_id);
+        assert!(fs.expanded.read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn preopen_readme_lists_the_readme_first() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        fs.store_listing(repo_id, repo_id, &entries(&[("src", "dir"), ("a.txt", "file"), ("README.md", "file")]));
+        let names = |fs: &GitHubFS| -> Vec<String> {
+            fs.directory_entries(repo_id, false).unwrap().into_iter().map(|(_inode, _kind, name)| name).collect()
+        };
+
+        assert_eq!(names(&fs), [".", "..", "src", "a.txt", "README.md"]);
+        fs.options.preopen_readme = true;
+        assert_eq!(names(&fs), [".", "..", "README.md", "src", "a.txt"]);
+    }
+
+    #[test]
+    fn json_listing_describes_the_directory_entries() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.with_json_listing = true;
+        let repo_id = add_hello_world(&fs);
+        let files: Vec<GitHubFile> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+        fs.store_listing(repo_id, repo_id, &files);
+
+        let entries = fs.directory_entries(repo_id, false).unwrap();
+        let (listing, _kind, name) = &entries[2];
+        assert_eq!(name, JSON_LISTING_NAME);
+        let json = fs.listing_json(repo_id).unwrap();
+        assert_eq!(fs.attr(*listing).unwrap().size, json.len() as u64);
+        let parsed: Vec<serde_json::Value> = serde_json::from_slice(&json).unwrap();
+        let described: Vec<(&str, &str, &str)> = parsed.iter()
+            .map(|entry| (entry["name"].as_str().unwrap(), entry["type"].as_str().unwrap(), entry["mode"].as_str().unwrap()))
+            .collect();
+        assert_eq!(described, [("README.md", "file", "100644"), ("src", "dir", "040000")]);
+        assert_eq!(parsed[0]["size"], files[0].size);
+        assert_eq!(parsed[0]["sha"], files[0].sha.as_str());
+    }
+
+    #[test]
+    fn access_grants_reads_and_denies_writes() {
+        let server = Server::new();
+        let mut fs = mock_fs(&server, None);
+        fs.options.uid = 1000;
+        fs.options.gid = 100;
+        let repo_id = add_hello_world(&fs);
+        let files: Vec<GitHubFile> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+        fs.store_listing(repo_id, repo_id, &files);
+        let readme = fs.path_inode(repo_id, "README.md");
+        let src = fs.path_inode(repo_id, "src");
+
+        assert_eq!(fs.check_access(readme, 1000, 100, libc::R_OK), Ok(()));
+        assert_eq!(fs.check_access(readme, 1000, 100, libc::W_OK), Err(EROFS));
+        assert_eq!(fs.check_access(readme, 1000, 100, libc::X_OK), Err(EACCES));
+        assert_eq!(fs.check_access(src, 2000, 200, libc::R_OK | libc::X_OK), Ok(()));
+        assert_eq!(fs.check_access(src, 0, 0, libc::W_OK), Err(EROFS));
+        assert_eq!(fs.check_access(readme, 0, 0, libc::X_OK), Err(EACCES));
+    }
+
+    #[test]
+    fn replacing_a_file_with_a_directory_bumps_its_generation() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let mut files: Vec<GitHubFile> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+
+        fs.store_listing(repo_id, repo_id, &files);
+        let readme = fs.path_inode(repo_id, "README.md");
+        fs.store_listing(repo_id, repo_id, &files);
+        assert_eq!(fs.generation(readme), 0);
+
+        files[0].file_type = "dir".to_string();
+        fs.forget_listings(repo_id);
+        fs.store_listing(repo_id, repo_id, &files);
+        assert_eq!(fs.path_inode(repo_id, "README.md"), readme);
+        assert_eq!(fs.generation(readme), 1);
+        assert_eq!(fs.generation(fs.path_inode(repo_id, "src")), 0);
+    }
+
+    #[test]
+    fn github_fs_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<GitHubFS>();
+    }
+
+    #[test]
+    fn concurrent_listings_share_inodes() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        server.mock("GET", "/repos/octocat/hello-world/contents/")
+            .match_query(Matcher::Any)
+            .with_body(fixture("contents_root.json"))
+            .create();
+
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let inodes: Vec<u64> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| {
+                    fs.directory_listing(repo_id).unwrap();
+                    fs.path_inode(repo_id, "README.md")
+                }))
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+
+        assert!(inodes.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn concurrent_listings_renames_and_reads_do_not_deadlock() {
+        let server = Server::new();
+        let fs = mock_fs(&server, None);
+        let repo_id = add_hello_world(&fs);
+        let files: Vec<GitHubFile> = serde_json::from_str(&fixture("contents_root.json")).unwrap();
+        let readme = fs.path_inode(repo_id, "README.md");
+        fs.renames.lock().unwrap().insert("octocat/hello-world".to_string(), "octocat/hello-universe".to_string());
+
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let (fs, files) = (&fs, &files);
+                scope.spawn(move || {
+                    for round in 0..200 {
+                        match (worker + round) % 4 {
+                            0 => fs.store_listing(repo_id, repo_id, files),
+                            1 => fs.forget_listings(repo_id),
+                            2 => assert_eq!(fs.sync_repo_name(repo_id).unwrap(), "octocat/hello-universe"),
+                            _ => {
+                                fs.attr(readme).unwrap();
+                                fs.xattrs(readme);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        fs.store_listing(repo_id, repo_id, &files);
+        assert_eq!(fs.path_inode(repo_id, "README.md"), readme);
+        assert_eq!(fs.locate(readme), Some(("octocat/hello-universe".to_string(), "README.md".to_string())));
+    }
+
+    #[test]
+    fn http_status_to_errno_maps_api_failures() {
+        assert_eq!(http_status_to_errno(StatusCode::UNAUTHORIZED), EACCES);
+        assert_eq!(http_status_to_errno(StatusCode::FORBIDDEN), EACCES);
+        assert_eq!(http_status_to_errno(StatusCode::NOT_FOUND), ENOENT);
+        assert_eq!(http_status_to_errno(StatusCode::TOO_MANY_REQUESTS), EAGAIN);
+        assert_eq!(http_status_to_errno(StatusCode::BAD_GATEWAY), EIO);
+        assert_eq!(http_status_to_errno(StatusCode::SERVICE_UNAVAILABLE), EIO);
+    }
+
+    #[test]
+    fn failed_fetches_reply_with_the_errno_of_their_status() {
+        let mut server = Server::new();
+        mock_head(&mut server);
+        for (path, status) in [("secret.txt", 401), ("forbidden.txt", 403), ("missing.txt", 404), ("broken.txt", 502)] {
+            server.mock("GET", format!("/repos/octocat/hello-world/contents/{}", path).as_str())
+                .match_query(Matcher::Any)
+                .with_status(status)
+                .with_body(r#"{"message": "failed"}"#)
+                .create();
+        }
+        let fs = mock_fs(&server, None);
+        let errno = |path: &str| errno_for(&fs.fetch_file_content("octocat/hello-world", path).unwrap_err());
+
+        assert_eq!(errno("secret.txt"), EACCES);
+        assert_eq!(errno("forbidden.txt"), EACCES);
+        assert_eq!(errno("missing.txt"), ENOENT);
+        assert_eq!(errno("broken.txt"), EIO);
+    }
+
+    #[test]
+    fn request_helpers_set_the_headers_and_report_the_status() {
+        let mut server = Server::new();
+        let user = server.mock("GET", "/user")
+            .match_header("accept", GITHUB_JSON)
+            .match_header("authorization", "Bearer secret")
+            .match_header("user-agent", "GitHubFS")
+            .match_header("x-github-api-version", DEFAULT_API_VERSION)
+            .with_body(fixture("user.json"))
+            .expect(1)
+            .create();
+        let raw = server.mock("GET", "/raw")
+            .match_header("accept", "application/vnd.github.raw")
+            .with_body("raw bytes")
+            .create();
+        server.mock("GET", "/missing")
+            .with_status(404)
+            .with_body(r#"{"message": "Not Found"}"#)
+            .create();
+        let fs = mock_fs(&server, Some("secret"));
+
+        let login: AuthenticatedUser = fs.get_json(&format!("{}/user", server.url())).unwrap();
+        assert_eq!(login.login, "octocat");
+        assert_eq!(fs.get_bytes(&format!("{}/raw", server.url()), "application/vnd.github.raw").unwrap(), b"raw bytes");
+        let err = fs.get_json::<AuthenticatedUser>(&format!("{}/missing", server.url())).unwrap_err();
+        assert_eq!(api_status(&err), Some(StatusCode::NOT_FOUND));
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        user.assert();
+        raw.assert();
+    }
+
+    #[test]
+    fn errno_for_prefers_rate_limits_over_forbidden() {
+        let rate_limited = io::Error::new(ErrorKind::WouldBlock, RateLimited("limit exhausted".to_string()));
+        assert_eq!(errno_for(&rate_limited), EAGAIN);
+        assert_eq!(errno_for(&io::Error::new(ErrorKind::TimedOut, "slow")), ETIMEDOUT);
+        assert_eq!(errno_for(&io::Error::other("broken")), EIO);
     }
 }
@@ -0,0 +1,53 @@
+use crate::codeowners::{glob_match, normalize_pattern};
+
+/// Where the repository-wide attributes file lives.
+pub const GITATTRIBUTES_PATH: &str = ".gitattributes";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    /// `Some(true)` for `binary`/`-text`, `Some(false)` for `text`, `None`
+    /// for `text=auto` and `!text`, which leave the decision to the heuristic.
+    binary: Option<bool>,
+}
+
+/// Text/binary classification from the root `.gitattributes` of a
+/// repository. The last matching line wins, as in git.
+#[derive(Debug, Clone, Default)]
+pub struct GitAttributes {
+    rules: Vec<Rule>,
+}
+
+impl GitAttributes {
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = normalize_pattern(fields.next()?);
+                // Only the attributes that decide between text and binary matter
+                let binary = fields.fold(None, |current, attribute| match attribute {
+                    "binary" | "-text" => Some(Some(true)),
+                    "text" => Some(Some(false)),
+                    "text=auto" | "!text" => Some(None),
+                    _ => current,
+                })?;
+                Some(Rule { pattern, binary })
+            })
+            .collect();
+        GitAttributes { rules }
+    }
+
+    /// Returns whether a repo-relative path is binary according to the
+    /// attributes, or `None` when they don't say.
+    pub fn is_binary(&self, path: &str) -> Option<bool> {
+        let path = path.trim_start_matches('/');
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| glob_match(rule.pattern.as_bytes(), path.as_bytes()))
+            .and_then(|rule| rule.binary)
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Pointer files are small; anything larger is real content.
+const MAX_POINTER_SIZE: usize = 1024;
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// Media type of LFS batch API requests and responses.
+pub const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// Object named by a Git LFS pointer file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LfsPointer {
+    /// SHA-256 of the object, without the `sha256:` prefix.
+    pub oid: String,
+    pub size: u64,
+}
+
+impl LfsPointer {
+    /// Parses the contents of a pointer file, returning `None` for anything
+    /// that isn't one.
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        if content.len() > MAX_POINTER_SIZE {
+            return None;
+        }
+        let text = std::str::from_utf8(content).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != POINTER_VERSION {
+            return None;
+        }
+        let mut oid = None;
+        let mut size = None;
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("oid", value)) => oid = value.strip_prefix("sha256:").map(str::to_string),
+                Some(("size", value)) => size = value.parse().ok(),
+                _ => {}
+            }
+        }
+        let oid = oid.filter(|oid| oid.len() == 64 && oid.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        Some(LfsPointer { oid, size: size? })
+    }
+}
+
+/// LFS batch endpoint of a repository on the GitHub web host `web_url`.
+pub fn batch_url(web_url: &str, repo_full_name: &str) -> String {
+    format!("{}/{}.git/info/lfs/objects/batch", web_url, repo_full_name)
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchRequest {
+    operation: &'static str,
+    transfers: Vec<&'static str>,
+    objects: Vec<BatchObject>,
+}
+
+impl BatchRequest {
+    /// Download request for a single object over the basic transfer.
+    pub fn download(pointer: &LfsPointer) -> Self {
+        BatchRequest {
+            operation: "download",
+            transfers: vec!["basic"],
+            objects: vec![BatchObject { oid: pointer.oid.clone(), size: pointer.size }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchObject {
+    oid: String,
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchResponse {
+    pub objects: Vec<BatchResponseObject>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchResponseObject {
+    pub oid: String,
+    pub actions: Option<BatchActions>,
+    pub error: Option<BatchError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchActions {
+    pub download: Option<BatchAction>,
+}
+
+/// Where to download an object from, and the headers to send.
+#[derive(Debug, Deserialize)]
+pub struct BatchAction {
+    pub href: String,
+    #[serde(default)]
+    pub header: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchError {
+    pub code: u16,
+    pub message: String,
+}
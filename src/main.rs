@@ -1,48 +1,415 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use fuser::MountOption;
 use log::{debug, error, info};
-use std::io::{self, Error, ErrorKind};
+use std::io;
 use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
 
+mod bench;
+mod cache;
+mod codeowners;
+mod config;
+mod content_cache;
+mod control;
 mod fs;
+mod gitattributes;
+mod lfs;
+mod progress;
+mod transform;
+mod validate;
 
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The owner of the GitHub repository.
-    owner: String,
+    #[arg(required_unless_present_any = ["org", "config"], conflicts_with = "org")]
+    owner: Option<String>,
+
+    /// Read settings from this JSON file. Its keys mirror the flags (`owner`,
+    /// `org`, `token`, `mountpoint`, `ref`, `api_url`, `cache_dir`,
+    /// `cache_size_mb`, `persist_cache`); flags on the command line win.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Directory to mount on, created if missing.
+    #[arg(long, value_name = "PATH")]
+    mountpoint: Option<PathBuf>,
+
+    /// Mount the repositories of this organization instead of a user's.
+    #[arg(long, value_name = "NAME")]
+    org: Option<String>,
+
+    /// Base URL of the GitHub REST API, e.g. `https://ghe.example.com/api/v3`
+    /// for GitHub Enterprise Server.
+    #[arg(long, value_name = "URL", default_value = fs::GITHUB_API_URL)]
+    api_url: String,
+
+    /// REST API version sent in `X-GitHub-Api-Version`. An empty value omits
+    /// the header, for servers that don't support versioning.
+    #[arg(long, value_name = "VERSION", default_value = fs::DEFAULT_API_VERSION)]
+    api_version: String,
 
-    /// The filesystem options.
+    /// Mount options: `ro`, `noexec`, `allow_other`, `allow_root`,
+    /// `default_permissions`, etc.
     #[arg(short, long)]
     options: Vec<String>,
-}
 
-fn ensure_mountpoint(mountpoint: &PathBuf) -> io::Result<()> {
-    if !mountpoint.exists() {
-        create_dir_all(mountpoint)?;
-    }
-    Ok(())
+    /// Collapse directories at or below this depth into a placeholder entry
+    /// in listings. Children are still reachable by name. Unlimited by default.
+    #[arg(long)]
+    max_depth_display: Option<usize>,
+
+    /// List each repository's README first when entering the repository.
+    #[arg(long)]
+    preopen_readme: bool,
+
+    /// Expose a `.listing.json` file in every directory describing its entries.
+    #[arg(long)]
+    with_json_listing: bool,
+
+    /// Expose a `.sizes` file in every directory with human-readable sizes.
+    #[arg(long)]
+    with_sizes: bool,
+
+    /// Expose each repository description as a `.description` file.
+    #[arg(long)]
+    with_descriptions: bool,
+
+    /// Reload a repository when its default branch changes upstream.
+    #[arg(long)]
+    follow_default_branch_changes: bool,
+
+    /// Expose each file's CODEOWNERS owners as the `user.github.codeowners` xattr.
+    #[arg(long)]
+    with_codeowners: bool,
+
+    /// How to name repositories whose names clash in the mount root.
+    #[arg(long, value_enum, default_value_t = fs::CollisionStrategy::Suffix)]
+    collision_strategy: fs::CollisionStrategy,
+
+    /// Download small files while their directory is loaded.
+    #[arg(long)]
+    prefetch: bool,
+
+    /// Largest file, in bytes, downloaded by `--prefetch`.
+    #[arg(long, default_value_t = fs::DEFAULT_PREFETCH_CONTENT_MAX)]
+    prefetch_content_max: u64,
+
+    /// Quarantine a repository after this many consecutive load failures.
+    /// Send SIGHUP to retry quarantined repositories immediately.
+    #[arg(long, value_name = "N")]
+    repo_blocklist_on_error: Option<u32>,
+
+    /// Additional root certificate (PEM or DER) to trust, e.g. a corporate CA.
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+
+    /// Disable TLS certificate verification. Only for test environments.
+    #[arg(long)]
+    insecure: bool,
+
+    /// Send every request through this proxy, overriding `HTTPS_PROXY` and
+    /// `HTTP_PROXY`.
+    #[arg(long, value_name = "URL", conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+
+    /// Connect directly, ignoring the proxy environment variables.
+    #[arg(long)]
+    no_proxy: bool,
+
+    /// Give up on a request after this many seconds. The operation that
+    /// needed it fails with ETIMEDOUT.
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    timeout_secs: u64,
+
+    /// Give up connecting to the API after this many seconds.
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    connect_timeout_secs: u64,
+
+    /// Retry a request up to this many times after a connection error or a
+    /// 5xx response, waiting longer each time.
+    #[arg(long, value_name = "N", default_value_t = fs::DEFAULT_MAX_RETRIES)]
+    max_retries: u32,
+
+    /// Line endings served for text files. Binary files are never changed.
+    #[arg(long, value_enum, default_value_t = transform::LineEndings::Preserve)]
+    line_endings: transform::LineEndings,
+
+    /// Expose the latest GitHub Actions run conclusion as `.meta/ci_status`.
+    #[arg(long)]
+    with_ci: bool,
+
+    /// Expose the SPDX identifier of each repository license as `.meta/license`.
+    #[arg(long)]
+    with_license: bool,
+
+    /// Only mount repositories under this SPDX license (e.g. `MIT`). Repeat or
+    /// separate with commas to accept several.
+    #[arg(long = "license", value_name = "SPDX", value_delimiter = ',')]
+    licenses: Vec<String>,
+
+    /// Owner of every file in the mount. Defaults to the user running
+    /// GitHubFS.
+    #[arg(long)]
+    uid: Option<u32>,
+
+    /// Group of every file in the mount. Defaults to the group running
+    /// GitHubFS.
+    #[arg(long)]
+    gid: Option<u32>,
+
+    /// Mount at most this many repositories (after the filters), stopping
+    /// the listing early on large accounts.
+    #[arg(long, value_name = "N")]
+    max_repos: Option<usize>,
+
+    /// Only mount repositories matching this glob (`*`, `?`), checked against
+    /// the name, or against `owner/name` when it contains a `/`. Repeatable.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Don't mount repositories matching this glob, even when an `--include`
+    /// matches them. Repeatable.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Don't mount archived repositories.
+    #[arg(long)]
+    no_archived: bool,
+
+    /// Don't mount forks.
+    #[arg(long)]
+    no_forks: bool,
+
+    /// Mount only private repositories.
+    #[arg(long)]
+    only_private: bool,
+
+    /// Show only the README of each repository instead of its files. Much
+    /// faster to mount and list across many repositories.
+    #[arg(long)]
+    readme_only: bool,
+
+    /// Load the root of every repository in parallel while mounting.
+    #[arg(long)]
+    warm_on_mount: bool,
+
+    /// Warm at most this many repositories with `--warm-on-mount`. The rest
+    /// are loaded when first listed.
+    #[arg(long, value_name = "N")]
+    warm_limit: Option<usize>,
+
+    /// Merge these branches into one tree. Files present in several branches
+    /// come from the first one listed; `user.github.branch` names the origin.
+    #[arg(long, value_name = "BRANCHES", value_delimiter = ',')]
+    union_branches: Vec<String>,
+
+    /// Mount this branch, tag or commit SHA instead of the default branch.
+    #[arg(long = "ref", value_name = "REF", conflicts_with = "union_branches")]
+    reference: Option<String>,
+
+    /// Show submodules as small text files with their pinned commit and URL
+    /// instead of empty files.
+    #[arg(long)]
+    include_submodule_gitlink_content: bool,
+
+    /// Wait this many seconds before refetching a directory or file that
+    /// failed, doubling the wait after every further failure.
+    #[arg(long, value_name = "SECONDS")]
+    refetch_interval_on_error: Option<u64>,
+
+    /// Fetch the start of a file with range requests so `head` and pagers
+    /// don't wait for the whole download.
+    #[arg(long)]
+    open_in_pager: bool,
+
+    /// Leave files with binary extensions (images, archives, ...) out of
+    /// directory listings. They stay reachable by name.
+    #[arg(long)]
+    hide_binary: bool,
+
+    /// Like `--hide-binary`, but also download files to detect binary content.
+    #[arg(long)]
+    hide_binary_strict: bool,
+
+    /// Always fetch files and directories under this mount-relative prefix
+    /// (`repo/path`) fresh, bypassing the caches. May be repeated.
+    #[arg(long = "live-prefix", value_name = "PATH")]
+    live_prefixes: Vec<String>,
+
+    /// Don't look up the date of the last commit touching each file, and
+    /// show every timestamp as 1970.
+    #[arg(long)]
+    no_commit_times: bool,
+
+    /// Replace Git LFS pointer files with the objects they point to. Costs
+    /// two extra requests per LFS file.
+    #[arg(long)]
+    resolve_lfs: bool,
+
+    /// Background requests the kernel may keep in flight.
+    #[arg(long, default_value_t = fs::DEFAULT_MAX_BACKGROUND)]
+    max_background: u16,
+
+    /// Background requests at which the kernel starts throttling.
+    #[arg(long, default_value_t = fs::DEFAULT_CONGESTION_THRESHOLD)]
+    congestion_threshold: u16,
+
+    /// Abort any API response whose body is larger than this many bytes.
+    #[arg(long, value_name = "BYTES", default_value_t = fs::DEFAULT_MAX_RESPONSE_SIZE)]
+    max_response_size: u64,
+
+    /// Size cap of the in-memory caches, in megabytes: half for file
+    /// contents, the rest for transformed copies and ETag responses. Least
+    /// recently used entries are evicted first.
+    #[arg(long, value_name = "MB", default_value_t = fs::DEFAULT_CACHE_SIZE_MB)]
+    cache_size_mb: u64,
+
+    /// Abort the mount with exit status 75 when the API rate limit runs out
+    /// while listing or warming the repositories.
+    #[arg(long, conflicts_with = "wait_for_ratelimit")]
+    fail_on_rate_limit: bool,
+
+    /// When the API rate limit runs out, sleep until it resets and retry
+    /// instead of failing with EAGAIN.
+    #[arg(long)]
+    wait_for_ratelimit: bool,
+
+    /// Check every SECONDS that the token is still valid, warning as soon as
+    /// it is revoked.
+    #[arg(long, value_name = "SECONDS")]
+    keepalive_interval: Option<u64>,
+
+    /// Expire cached directory listings every SECONDS, so that files pushed
+    /// after mounting show up on the next `ls`.
+    #[arg(long, value_name = "SECONDS")]
+    refresh_interval_secs: Option<u64>,
+
+    /// Keep answering ENOENT for a name that wasn't found for this many
+    /// seconds without looking it up again. 0 disables the negative cache.
+    #[arg(long, value_name = "SECONDS", default_value_t = fs::DEFAULT_NEGATIVE_TTL_SECS)]
+    negative_ttl_secs: u64,
+
+    /// After mounting, list the root and read the smallest file to confirm
+    /// the mount works, logging the outcome.
+    #[arg(long)]
+    validate_mount: bool,
+
+    /// Unix socket accepting runtime commands (`refresh`, `stats`,
+    /// `clear-cache`, `reload-token`), one per line.
+    #[arg(long, value_name = "PATH")]
+    control_socket: Option<PathBuf>,
+
+    /// Save ETags in the cache directory on unmount and reuse them on the
+    /// next mount, revalidating each with a conditional request.
+    #[arg(long, overrides_with = "no_persist_cache")]
+    persist_cache: bool,
+
+    /// Don't persist the cache, even when the config file enables it.
+    #[arg(long, overrides_with = "persist_cache")]
+    no_persist_cache: bool,
+
+    /// With `--persist-cache`, also save downloaded file contents, keyed by
+    /// blob SHA.
+    #[arg(long)]
+    persist_contents: bool,
+
+    /// Don't show the `--warm-on-mount` progress while mounting.
+    #[arg(long)]
+    quiet: bool,
+
+    /// GitHub token used to authenticate. Defaults to `$GITHUB_TOKEN`; with
+    /// neither, only the owner's public repositories are mounted.
+    #[arg(long)]
+    token: Option<String>,
+
+    /// Read the token from PATH instead. `reload-token` on the control
+    /// socket rereads it, e.g. after rotating the token.
+    #[arg(long, value_name = "PATH", conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+
+    /// Directory for the persistent cache (`cache` subcommand and
+    /// `--persist-cache`). Defaults to `$XDG_CACHE_HOME/githubfs`.
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
 }
 
-fn main() -> io::Result<()> {
-    env_logger::init();
-    let args = Args::parse();
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect or purge the persistent cache.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Measure listing, tree walk and read times for one repository.
+    Bench {
+        /// Repository to benchmark, as `owner/name`.
+        repo: String,
 
-    let mountpoint = PathBuf::from("/mnt/githubfs");
-    ensure_mountpoint(&mountpoint)?;
+        /// Only read files up to this size, in bytes.
+        #[arg(long, default_value_t = 1024 * 1024)]
+        max_file_size: u64,
+    },
+}
 
-    let github_token = "Seu token".to_string();
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Show the number of cached entries and their total size.
+    Info,
+    /// Remove every cached entry.
+    Clear,
+}
 
-    let mut fs = fs::GitHubFS::new(args.owner.clone(), github_token)?;
+fn run_cache_command(action: &CacheAction, cache_dir: &Path) -> io::Result<()> {
+    match action {
+        CacheAction::Info => {
+            let summary = cache::info(cache_dir)?;
+            println!("Cache directory: {}", cache_dir.display());
+            println!("Entries: {}", summary.entries);
+            println!("Size: {} bytes", summary.bytes);
+        }
+        CacheAction::Clear => {
+            let removed = cache::clear(cache_dir)?;
+            println!("Removed {} entries from {}", removed, cache_dir.display());
+        }
+    }
+    Ok(())
+}
 
-    // Carrega repositórios no início
-    if let Err(e) = fs.fetch_repositories() {
-        error!("Error loading repositories: {:?}", e);
-        return Err(e);
+/// Takes the token from `--token`, `--token-file` or, when both are absent,
+/// from `GITHUB_TOKEN`. `None` when none is set, for unauthenticated access.
+fn github_token(flag: Option<&str>, file: Option<&Path>) -> io::Result<Option<String>> {
+    if let Some(path) = file {
+        return control::read_token_file(path).map(Some);
+    }
+    let token = match flag {
+        Some(token) => token.to_string(),
+        None => match std::env::var(TOKEN_ENV_VAR) {
+            Ok(token) => token,
+            Err(_) => {
+                info!("No GitHub token given (--token or {}), using unauthenticated requests", TOKEN_ENV_VAR);
+                return Ok(None);
+            }
+        },
+    };
+    if token.trim().is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "The GitHub token is empty"));
     }
+    Ok(Some(token))
+}
 
+/// Maps the `-o` options to mount options. The mount is always read-only,
+/// so `rw` is refused.
+fn parse_mount_options(opts: &[String]) -> io::Result<Vec<MountOption>> {
     let mut options = Vec::new();
-    for opt in &args.options {
+    for opt in opts {
         debug!("Parsing option {}", opt);
         let fsopt = match opt.as_str() {
             "dev" => MountOption::Dev,
@@ -57,6 +424,9 @@ fn main() -> io::Result<()> {
             "dirsync" => MountOption::DirSync,
             "sync" => MountOption::Sync,
             "async" => MountOption::Async,
+            "allow_other" => MountOption::AllowOther,
+            "allow_root" => MountOption::AllowRoot,
+            "default_permissions" => MountOption::DefaultPermissions,
             "rw" => {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
@@ -72,15 +442,384 @@ fn main() -> io::Result<()> {
         };
         options.push(fsopt);
     }
+    if options.contains(&MountOption::AllowOther) && options.contains(&MountOption::AllowRoot) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Options allow_other and allow_root are mutually exclusive",
+        ));
+    }
+    Ok(options)
+}
 
-    debug!("Mounting filesystem at {:?}", mountpoint);
-    match fuser::mount2(fs, &mountpoint, &options) {
-        Ok(_) => println!("Filesystem mounted successfully"),
-        Err(e) => {
-            error!("Failed to mount filesystem: {:?}", e);
-            return Err(e);
+/// Fills in the settings not given on the command line from `config`.
+/// Flags left at their default value count as not given.
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: config::Config) {
+    let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if args.owner.is_none() && args.org.is_none() {
+        args.owner = config.owner;
+        args.org = config.org;
+    }
+    args.token = args.token.take().or(config.token);
+    args.mountpoint = args.mountpoint.take().or(config.mountpoint);
+    // --union-branches on the command line excludes the ref
+    if args.union_branches.is_empty() {
+        args.reference = args.reference.take().or(config.reference);
+    }
+    args.cache_dir = args.cache_dir.take().or(config.cache_dir);
+    if let (false, Some(api_url)) = (from_command_line("api_url"), config.api_url) {
+        args.api_url = api_url;
+    }
+    if let (false, Some(cache_size_mb)) = (from_command_line("cache_size_mb"), config.cache_size_mb) {
+        args.cache_size_mb = cache_size_mb;
+    }
+    if let (false, false, Some(persist_cache)) =
+        (from_command_line("persist_cache"), args.no_persist_cache, config.persist_cache)
+    {
+        args.persist_cache = persist_cache;
+    }
+}
+
+/// Checks what clap can't once the config file is merged in.
+fn validate_args(args: &Args) -> io::Result<()> {
+    if args.command.is_some() {
+        return Ok(());
+    }
+    if args.persist_contents && !args.persist_cache {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--persist-contents requires --persist-cache, on the command line or in the config file",
+        ));
+    }
+    match (&args.owner, &args.org) {
+        (None, None) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "An owner or --org is required, on the command line or in the config file",
+        )),
+        (Some(_), Some(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The owner and --org are mutually exclusive",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn ensure_mountpoint(mountpoint: &PathBuf) -> io::Result<()> {
+    if !mountpoint.exists() {
+        create_dir_all(mountpoint)?;
+    }
+    Ok(())
+}
+
+/// Mountpoint used when neither `--mountpoint` nor the config file sets one.
+const DEFAULT_MOUNTPOINT: &str = "/mnt/githubfs";
+
+/// Environment variable read when `--token` is not given.
+const TOKEN_ENV_VAR: &str = "GITHUB_TOKEN";
+
+/// Filesystem type shown by `mount`, as `fuse.githubfs`, and prefix of the
+/// source name (`githubfs:<owner>`).
+const FS_TYPE: &str = "githubfs";
+
+/// Exit status when `--fail-on-rate-limit` aborts the mount (EX_TEMPFAIL).
+const RATE_LIMIT_EXIT_CODE: i32 = 75;
+
+/// How often the main thread checks for a termination signal while mounted.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Set by the SIGINT/SIGTERM handler to unmount and exit.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_reload(_signal: libc::c_int) {
+    fs::RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn request_shutdown(_signal: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Waits until a termination signal arrives or the filesystem is unmounted
+/// from outside, then unmounts and waits for the session to finish.
+fn serve_until_shutdown(session: fuser::BackgroundSession) {
+    while !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) && !session.guard.is_finished() {
+        thread::sleep(SHUTDOWN_POLL_INTERVAL);
+    }
+    if SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+        info!("Termination signal received, unmounting {:?}", session.mountpoint);
+    }
+    // Unmounting before exiting avoids "Transport endpoint is not connected"
+    session.join();
+}
+
+fn main() -> io::Result<()> {
+    env_logger::init();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(path) = args.config.clone() {
+        let config = config::Config::load(&path)?;
+        debug!("Loaded settings from {:?}", path);
+        apply_config(&mut args, &matches, config);
+    }
+    validate_args(&args)?;
+
+    let cache_dir = args.cache_dir.clone().unwrap_or_else(cache::default_cache_dir);
+    if let Some(Command::Cache { action }) = &args.command {
+        return run_cache_command(action, &cache_dir);
+    }
+
+    let github_token = github_token(args.token.as_deref(), args.token_file.as_deref())?;
+
+    let fs_options = fs::FsOptions {
+        max_depth_display: args.max_depth_display,
+        preopen_readme: args.preopen_readme,
+        with_json_listing: args.with_json_listing,
+        with_sizes: args.with_sizes,
+        with_descriptions: args.with_descriptions,
+        follow_default_branch_changes: args.follow_default_branch_changes,
+        with_codeowners: args.with_codeowners,
+        collision_strategy: args.collision_strategy,
+        prefetch: args.prefetch,
+        prefetch_content_max: args.prefetch_content_max,
+        quarantine_after: args.repo_blocklist_on_error,
+        ca_cert: args.ca_cert.clone(),
+        insecure: args.insecure,
+        proxy: args.proxy.clone(),
+        no_proxy: args.no_proxy,
+        timeout: Some(Duration::from_secs(args.timeout_secs)),
+        connect_timeout: Some(Duration::from_secs(args.connect_timeout_secs)),
+        max_retries: args.max_retries,
+        line_endings: args.line_endings,
+        with_ci: args.with_ci,
+        with_license: args.with_license,
+        licenses: args.licenses.clone(),
+        // getuid/getgid always succeed
+        uid: args.uid.unwrap_or_else(|| unsafe { libc::getuid() }),
+        gid: args.gid.unwrap_or_else(|| unsafe { libc::getgid() }),
+        max_repos: args.max_repos,
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        no_archived: args.no_archived,
+        no_forks: args.no_forks,
+        only_private: args.only_private,
+        readme_only: args.readme_only,
+        warm_on_mount: args.warm_on_mount,
+        warm_limit: args.warm_limit,
+        union_branches: args.union_branches.clone(),
+        reference: args.reference.clone(),
+        org: args.org.clone(),
+        api_url: Some(args.api_url.clone()),
+        api_version: Some(args.api_version.clone()).filter(|version| !version.is_empty()),
+        include_submodule_gitlink_content: args.include_submodule_gitlink_content,
+        refetch_interval_on_error: args.refetch_interval_on_error.map(Duration::from_secs),
+        open_in_pager: args.open_in_pager,
+        hide_binary: args.hide_binary,
+        hide_binary_strict: args.hide_binary_strict,
+        live_prefixes: args.live_prefixes.clone(),
+        resolve_lfs: args.resolve_lfs,
+        commit_times: !args.no_commit_times,
+        max_background: args.max_background,
+        congestion_threshold: args.congestion_threshold,
+        startup_progress: !args.quiet,
+        max_response_size: args.max_response_size,
+        cache_size: args.cache_size_mb * 1024 * 1024,
+        fail_on_rate_limit: args.fail_on_rate_limit,
+        wait_for_ratelimit: args.wait_for_ratelimit,
+        keepalive_interval: args.keepalive_interval.map(Duration::from_secs),
+        persist_dir: args.persist_cache.then(|| cache_dir.clone()),
+        persist_contents: args.persist_contents,
+        refresh_interval: args.refresh_interval_secs.filter(|secs| *secs > 0).map(Duration::from_secs),
+        negative_ttl: Some(Duration::from_secs(args.negative_ttl_secs)).filter(|ttl| !ttl.is_zero()),
+    };
+
+    if let Some(Command::Bench { repo, max_file_size }) = &args.command {
+        let owner = repo.split('/').next().unwrap_or_default().to_string();
+        let fs = fs::GitHubFS::unloaded(owner, github_token, fs_options)?;
+        let report = bench::run(&fs, repo, *max_file_size)?;
+        println!("{}", report);
+        return Ok(());
+    }
+    let owner = args.owner.clone()
+        .or_else(|| args.org.clone())
+        .expect("owner or --org is required when no subcommand is given");
+    // Names the mount in `mount` and /proc/mounts
+    let identity = [
+        MountOption::FSName(format!("{}:{}", FS_TYPE, owner)),
+        MountOption::Subtype(FS_TYPE.to_string()),
+    ];
+
+    let mountpoint = args.mountpoint.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_MOUNTPOINT));
+    ensure_mountpoint(&mountpoint)?;
+
+    // SIGHUP releases the quarantined repositories
+    unsafe {
+        libc::signal(libc::SIGHUP, request_reload as *const () as libc::sighandler_t);
+    }
+
+    let fs = match fs::GitHubFS::new(owner, github_token, fs_options) {
+        Ok(fs) => fs,
+        Err(err) if args.fail_on_rate_limit && fs::is_rate_limited(&err) => {
+            error!("Aborting mount: {}", err);
+            std::process::exit(RATE_LIMIT_EXIT_CODE);
+        }
+        Err(err) => {
+            error!("Failed to initialize GitHubFS: {}", err);
+            return Err(err);
         }
     };
 
+    if let Some(path) = &args.control_socket {
+        let shared = control::Shared {
+            metrics: fs.shared_metrics(),
+            token: fs.shared_token(),
+            token_file: args.token_file.clone(),
+        };
+        control::spawn(path, shared)?;
+    }
+
+    let mut options = parse_mount_options(&args.options)?;
+    options.extend(identity);
+
+    // Until here SIGINT and SIGTERM exit directly; from now on they unmount
+    unsafe {
+        libc::signal(libc::SIGINT, request_shutdown as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, request_shutdown as *const () as libc::sighandler_t);
+    }
+
+    debug!("Mounting filesystem at {:?}", mountpoint);
+    let session = fuser::spawn_mount2(fs, &mountpoint, &options).map_err(|e| {
+        error!("Failed to mount filesystem: {:?}", e);
+        e
+    })?;
+    println!("Filesystem mounted successfully");
+    if args.validate_mount {
+        match validate::run(&mountpoint) {
+            Ok(path) => info!("Mount validated: read {:?}", path),
+            Err(e) => error!("Mount validation failed: {}", e),
+        }
+    }
+    serve_until_shutdown(session);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(opts: &[&str]) -> io::Result<Vec<MountOption>> {
+        parse_mount_options(&opts.iter().map(|opt| opt.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_access_options() {
+        assert_eq!(
+            options(&["allow_other", "default_permissions"]).unwrap(),
+            [MountOption::AllowOther, MountOption::DefaultPermissions],
+        );
+        assert_eq!(options(&["allow_root"]).unwrap(), [MountOption::AllowRoot]);
+    }
+
+    #[test]
+    fn rejects_conflicting_and_writable_options() {
+        let err = options(&["allow_other", "allow_root"]).unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"));
+        assert_eq!(options(&["rw"]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(options(&["bogus"]).unwrap_err().to_string().contains("bogus"));
+    }
+
+    fn args_with_config(argv: &[&str], json: &str) -> Args {
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, config::Config::parse(json).unwrap());
+        args
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let json = r#"{"owner": "octocat", "ref": "main", "api_url": "https://ghe.example.com/api/v3", "cache_size_mb": 64}"#;
+
+        let args = args_with_config(&["githubfs", "--config", "githubfs.json", "--ref", "dev"], json);
+        assert_eq!(args.owner.as_deref(), Some("octocat"));
+        assert_eq!(args.reference.as_deref(), Some("dev"));
+        assert_eq!(args.api_url, "https://ghe.example.com/api/v3");
+        assert_eq!(args.cache_size_mb, 64);
+
+        let args = args_with_config(&["githubfs", "alice", "--cache-size-mb", "128"], json);
+        assert_eq!(args.owner.as_deref(), Some("alice"));
+        assert_eq!(args.cache_size_mb, 128);
+        validate_args(&args).unwrap();
+    }
+
+    #[test]
+    fn persist_cache_can_be_turned_off_over_the_config_file() {
+        let json = r#"{"owner": "octocat", "persist_cache": true}"#;
+        let persist = |flags: &[&str], json: &str| {
+            let argv: Vec<&str> = ["githubfs", "--config", "githubfs.json"].iter().chain(flags).copied().collect();
+            args_with_config(&argv, json).persist_cache
+        };
+
+        assert!(persist(&[], json));
+        assert!(!persist(&["--no-persist-cache"], json));
+        assert!(!persist(&["--persist-cache", "--no-persist-cache"], json));
+        assert!(persist(&["--no-persist-cache", "--persist-cache"], json));
+        assert!(!persist(&[], r#"{"owner": "octocat", "persist_cache": false}"#));
+    }
+
+    #[test]
+    fn persist_contents_accepts_persist_cache_from_the_config_file() {
+        let argv = ["githubfs", "--config", "githubfs.json", "--persist-contents"];
+
+        let args = args_with_config(&argv, r#"{"owner": "octocat", "persist_cache": true}"#);
+        assert!(args.persist_cache && args.persist_contents);
+        validate_args(&args).unwrap();
+
+        let args = args_with_config(&argv, r#"{"owner": "octocat"}"#);
+        assert_eq!(validate_args(&args).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn cache_clear_removes_only_cache_entries() {
+        let dir = std::env::temp_dir().join(format!("githubfs-cache-test-{}", std::process::id()));
+        let contents = dir.join("contents").join("git");
+        create_dir_all(&contents).unwrap();
+        std::fs::write(dir.join("etags.json"), b"[]").unwrap();
+        std::fs::write(contents.join("7fd1a60b01f91b314f59955a4e4d4e80d8edf11d"), b"Hello World!\n").unwrap();
+        std::fs::write(dir.join("etags.tmp"), b"[").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not ours").unwrap();
+
+        assert_eq!(cache::info(&dir).unwrap(), cache::CacheInfo { entries: 3, bytes: 16 });
+        assert_eq!(cache::clear(&dir).unwrap(), 3);
+        assert_eq!(cache::info(&dir).unwrap(), cache::CacheInfo::default());
+        assert!(dir.join("notes.txt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validation_fails_with_a_clear_message_on_a_broken_mount() {
+        let dir = std::env::temp_dir().join(format!("githubfs-validate-test-{}", std::process::id()));
+        let repo = dir.join("hello-world");
+        create_dir_all(repo.join("src")).unwrap();
+
+        let err = validate::run(&dir.join("missing")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with("Failed to list mount root"), "{}", err);
+        let err = validate::run(&repo.join("src")).unwrap_err();
+        assert!(err.to_string().contains("lists no repositories"), "{}", err);
+        std::fs::write(repo.join(".sizes"), b"synthetic").unwrap();
+        let err = validate::run(&dir).unwrap_err();
+        assert_eq!(err.to_string(), "No readable file found in 1 repositories; check the token scopes");
+
+        std::fs::write(repo.join("README.md"), b"Hello World!\n").unwrap();
+        std::fs::write(repo.join("LICENSE"), b"MIT\n").unwrap();
+        assert_eq!(validate::run(&dir).unwrap(), repo.join("LICENSE"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn config_without_owner_is_rejected() {
+        let args = args_with_config(&["githubfs", "--config", "githubfs.json"], r#"{"token": "secret"}"#);
+        assert_eq!(validate_args(&args).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(config::Config::parse(r#"{"owner": "octocat", "cache_size": 1}"#).is_err());
+    }
+}
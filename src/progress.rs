@@ -0,0 +1,45 @@
+use log::info;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// "Loaded X/Y repositories" counter shown while the mount starts. On a
+/// terminal the line is updated in place; otherwise progress is logged
+/// about every tenth of the way.
+pub struct LoadProgress {
+    total: usize,
+    loaded: AtomicUsize,
+    enabled: bool,
+    tty: bool,
+}
+
+impl LoadProgress {
+    pub fn new(total: usize, enabled: bool) -> Self {
+        LoadProgress {
+            total,
+            loaded: AtomicUsize::new(0),
+            enabled,
+            tty: io::stderr().is_terminal(),
+        }
+    }
+
+    /// Number of repositories counted so far.
+    pub fn loaded(&self) -> usize {
+        self.loaded.load(Ordering::Relaxed)
+    }
+
+    /// Counts one more repository as loaded, whether it succeeded or not.
+    pub fn advance(&self) {
+        let loaded = self.loaded.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.enabled {
+            return;
+        }
+        if self.tty {
+            eprint!("\rLoaded {}/{} repositories", loaded, self.total);
+            if loaded == self.total {
+                eprintln!();
+            }
+        } else if loaded == self.total || loaded.is_multiple_of((self.total / 10).max(1)) {
+            info!("Loaded {}/{} repositories", loaded, self.total);
+        }
+    }
+}
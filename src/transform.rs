@@ -0,0 +1,72 @@
+/// How many leading bytes are sampled when guessing if content is binary.
+const BINARY_SAMPLE_SIZE: usize = 8000;
+
+/// Extensions of files that are practically always binary.
+const BINARY_EXTENSIONS: [&str; 32] = [
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tiff",
+    "pdf", "zip", "gz", "tgz", "bz2", "xz", "7z", "rar", "jar", "war",
+    "exe", "dll", "so", "dylib", "o", "a", "class", "pyc", "wasm",
+    "mp3", "mp4", "mov", "woff", "woff2",
+];
+
+/// Line ending style applied to text files on read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEndings {
+    /// Convert CRLF to LF.
+    Lf,
+    /// Convert LF to CRLF.
+    Crlf,
+    /// Serve the bytes as stored on GitHub.
+    #[default]
+    Preserve,
+}
+
+/// Guesses whether content is binary by looking for a NUL byte near the start,
+/// the same heuristic git uses.
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(BINARY_SAMPLE_SIZE).any(|&byte| byte == 0)
+}
+
+/// Guesses whether a file is binary from its extension alone.
+pub fn has_binary_extension(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_stem, extension)| BINARY_EXTENSIONS.iter().any(|known| extension.eq_ignore_ascii_case(known)))
+}
+
+/// Rewrites the line endings of text content. Binary content is returned
+/// untouched.
+pub fn convert_line_endings(content: Vec<u8>, style: LineEndings) -> Vec<u8> {
+    if is_binary(&content) {
+        return content;
+    }
+    rewrite_line_endings(content, style)
+}
+
+/// Rewrites the line endings of content already known to be text.
+pub fn rewrite_line_endings(content: Vec<u8>, style: LineEndings) -> Vec<u8> {
+    if style == LineEndings::Preserve {
+        return content;
+    }
+
+    let mut converted = Vec::with_capacity(content.len());
+    let mut bytes = content.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'\r' if bytes.peek() == Some(&&b'\n') => {
+                // CRLF: emit the line ending in the chosen style
+                bytes.next();
+                push_newline(&mut converted, style);
+            }
+            b'\n' => push_newline(&mut converted, style),
+            _ => converted.push(byte),
+        }
+    }
+    converted
+}
+
+fn push_newline(out: &mut Vec<u8>, style: LineEndings) {
+    if style == LineEndings::Crlf {
+        out.push(b'\r');
+    }
+    out.push(b'\n');
+}
@@ -0,0 +1,54 @@
+use log::debug;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Lists the mount root and reads the smallest file found directly inside
+/// the repositories, to confirm listing and reading work end to end.
+/// Returns the file that was read.
+pub fn run(mountpoint: &Path) -> io::Result<PathBuf> {
+    let repos: Vec<PathBuf> = fs::read_dir(mountpoint)
+        .map_err(|err| io::Error::new(err.kind(), format!("Failed to list mount root {:?}: {}", mountpoint, err)))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    if repos.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Mount root {:?} lists no repositories; check the owner and token scopes", mountpoint),
+        ));
+    }
+
+    let mut smallest: Option<(u64, PathBuf)> = None;
+    for repo in &repos {
+        let entries = match fs::read_dir(repo) {
+            Ok(entries) => entries,
+            Err(err) => {
+                debug!("Skipping {:?} during validation: {}", repo, err);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            // Hidden files are synthetic and don't exercise the read path
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if smallest.as_ref().is_none_or(|(size, _path)| metadata.len() < *size) {
+                smallest = Some((metadata.len(), entry.path()));
+            }
+        }
+    }
+
+    let (_size, path) = smallest.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No readable file found in {} repositories; check the token scopes", repos.len()),
+        )
+    })?;
+    fs::read(&path)
+        .map_err(|err| io::Error::new(err.kind(), format!("Failed to read {:?}: {}", path, err)))?;
+    Ok(path)
+}